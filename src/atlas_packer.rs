@@ -0,0 +1,486 @@
+use crate::{BlitOptions, Image, SdlResult};
+use sdl3::{
+    rect::Rect,
+    render::{Texture, TextureCreator},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// Where a named image was packed: the page (texture) index and the area within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasRect {
+    pub page: usize,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRect {
+    /// The area within the page, as an SDL rect.
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Packs RGBA images into one or more square-ish pages using the skyline bottom-left
+/// algorithm. Pages start at "initial_size" and double in size as needed up to "max_size",
+/// then a new page is started. Each image is surrounded by "padding" transparent pixels to
+/// avoid bleeding when sampling with linear filtering.
+/// The result can be saved as a JSON sidecar plus one QOI file per page, and loaded back.
+#[derive(Debug, Clone)]
+pub struct AtlasPacker {
+    padding: u32,
+    initial_size: u32,
+    max_size: u32,
+    pages: Vec<Page>,
+    rects: BTreeMap<String, AtlasRect>,
+}
+
+#[derive(Debug, Clone)]
+struct Page {
+    image: Image,
+    // The top edge of the packed area, as contiguous horizontal segments from left to right.
+    skyline: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+// The JSON sidecar format.
+#[derive(Serialize, Deserialize)]
+struct AtlasFile {
+    padding: u32,
+    initial_size: u32,
+    max_size: u32,
+    pages: Vec<String>,
+    rects: BTreeMap<String, AtlasRect>,
+}
+
+impl Default for AtlasPacker {
+    /// Starts at 256x256 pixels, grows up to 4096x4096, with 1 pixel of padding.
+    fn default() -> Self {
+        Self::new(256, 4096, 1)
+    }
+}
+
+impl AtlasPacker {
+    /// An empty packer. "initial_size" is clamped to "max_size".
+    pub fn new(initial_size: u32, max_size: u32, padding: u32) -> Self {
+        Self {
+            padding,
+            initial_size: initial_size.clamp(1, max_size.max(1)),
+            max_size: max_size.max(1),
+            pages: Vec::new(),
+            rects: BTreeMap::new(),
+        }
+    }
+
+    /// Packs an image under "name", growing the current page or starting a new one if needed.
+    /// Fails if the name is already used or if the image can't fit even an empty page.
+    pub fn add(&mut self, name: impl Into<String>, image: &Image) -> SdlResult<AtlasRect> {
+        let name = name.into();
+        if self.rects.contains_key(&name) {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("Atlas already contains \"{}\"", name),
+            )));
+        }
+        let (width, height) = (image.width(), image.height());
+        if width + self.padding * 2 > self.max_size || height + self.padding * 2 > self.max_size {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("\"{}\" is larger than the maximum atlas size", name),
+            )));
+        }
+
+        let (page, x, y) = self.place(width, height);
+        self.pages[page]
+            .image
+            .frame()
+            .blit_ex(image, x as i32, y as i32, &BlitOptions::default());
+
+        let rect = AtlasRect {
+            page,
+            x: x as i32,
+            y: y as i32,
+            width,
+            height,
+        };
+        self.rects.insert(name, rect);
+        Ok(rect)
+    }
+
+    /// The rect an image was packed into.
+    pub fn get(&self, name: &str) -> Option<AtlasRect> {
+        self.rects.get(name).copied()
+    }
+
+    /// All packed rects, sorted by name.
+    pub fn rects(&self) -> &BTreeMap<String, AtlasRect> {
+        &self.rects
+    }
+
+    /// How many pages were created.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The pixels of a page.
+    pub fn page(&self, index: usize) -> Option<&Image> {
+        self.pages.get(index).map(|page| &page.image)
+    }
+
+    /// Creates one texture per page, in page order.
+    pub fn create_textures<T>(
+        &self,
+        texture_creator: &TextureCreator<T>,
+    ) -> SdlResult<Vec<Texture>> {
+        self.pages
+            .iter()
+            .map(|page| page.image.to_texture(texture_creator))
+            .collect()
+    }
+
+    /// Saves the rects to a JSON file at "path", and each page as a QOI image next to it
+    /// (for "atlas.json", the pages are "atlas_0.qoi", "atlas_1.qoi" and so on).
+    pub fn save(&self, path: impl AsRef<Path>) -> SdlResult<()> {
+        let path = path.as_ref();
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("atlas");
+        let mut pages = Vec::with_capacity(self.pages.len());
+        for (i, page) in self.pages.iter().enumerate() {
+            let file = format!("{}_{}.qoi", stem, i);
+            let encoded =
+                qoi::encode_to_vec(page.image.pixels(), page.image.width(), page.image.height())?;
+            std::fs::write(path.with_file_name(&file), encoded)?;
+            pages.push(file);
+        }
+        let sidecar = AtlasFile {
+            padding: self.padding,
+            initial_size: self.initial_size,
+            max_size: self.max_size,
+            pages,
+            rects: self.rects.clone(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&sidecar)?)?;
+        Ok(())
+    }
+
+    /// Loads an atlas saved with "save". More images can still be added to it.
+    pub fn load(path: impl AsRef<Path>) -> SdlResult<Self> {
+        let path = path.as_ref();
+        let sidecar: AtlasFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let mut result = Self::new(sidecar.initial_size, sidecar.max_size, sidecar.padding);
+        for file in &sidecar.pages {
+            let image = Image::load(path.with_file_name(file))?;
+            result.pages.push(Page {
+                skyline: Vec::new(),
+                image,
+            });
+        }
+        for (name, rect) in &sidecar.rects {
+            let Some(page) = result.pages.get(rect.page) else {
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("\"{}\" refers to a missing atlas page", name),
+                )));
+            };
+            let (width, height) = (page.image.width() as i64, page.image.height() as i64);
+            let (x, y) = (rect.x as i64, rect.y as i64);
+            if x < 0 || y < 0 || x + rect.width as i64 > width || y + rect.height as i64 > height {
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("\"{}\" is outside of its atlas page", name),
+                )));
+            }
+        }
+        result.rects = sidecar.rects;
+        for i in 0..result.pages.len() {
+            result.rebuild_skyline(i);
+        }
+        Ok(result)
+    }
+
+    // Finds a spot for a rect of this size, growing pages or adding a new one if needed.
+    // Returns the page index and the top-left corner.
+    fn place(&mut self, width: u32, height: u32) -> (usize, u32, u32) {
+        let (w, h) = (width + self.padding, height + self.padding);
+        loop {
+            for (i, page) in self.pages.iter_mut().enumerate() {
+                if let Some((x, y)) = page.insert(w, h) {
+                    return (i, x, y);
+                }
+            }
+            // Only the last page grows, earlier ones are already at their maximum size.
+            let can_grow = self.pages.last().is_some_and(|page| {
+                page.image.width() < self.max_size || page.image.height() < self.max_size
+            });
+            if can_grow {
+                let last = self.pages.len() - 1;
+                self.pages[last].grow(self.max_size, self.padding);
+            } else {
+                self.pages.push(Page::new(
+                    self.initial_size,
+                    self.initial_size,
+                    self.padding,
+                ));
+            }
+        }
+    }
+
+    // Recreates a page's skyline from its rects, for atlases loaded from disk.
+    fn rebuild_skyline(&mut self, index: usize) {
+        let page = &mut self.pages[index];
+        let (width, height) = (page.image.width(), page.image.height());
+        let mut tops = vec![self.padding.min(height); width as usize];
+        for rect in self.rects.values().filter(|rect| rect.page == index) {
+            // In bounds, checked by "load".
+            let bottom = (rect.y as u32 + rect.height)
+                .saturating_add(self.padding)
+                .min(height);
+            let right = (rect.x as u32 + rect.width)
+                .saturating_add(self.padding)
+                .min(width);
+            for top in &mut tops[rect.x as usize..right as usize] {
+                *top = (*top).max(bottom);
+            }
+        }
+        page.skyline.clear();
+        let start = self.padding.min(width);
+        for x in start..width {
+            match page.skyline.last_mut() {
+                Some(last) if last.y == tops[x as usize] => last.width += 1,
+                _ => page.skyline.push(Segment {
+                    x,
+                    y: tops[x as usize],
+                    width: 1,
+                }),
+            }
+        }
+    }
+}
+
+impl Page {
+    fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            image: Image::new(width, height),
+            skyline: vec![Segment {
+                x: padding.min(width),
+                y: padding.min(height),
+                width: width.saturating_sub(padding),
+            }],
+        }
+    }
+
+    // Doubles the smaller side, keeping the existing pixels.
+    fn grow(&mut self, max_size: u32, padding: u32) {
+        let (old_width, old_height) = (self.image.width(), self.image.height());
+        let (width, height) = if old_width <= old_height && old_width < max_size {
+            ((old_width * 2).min(max_size), old_height)
+        } else {
+            (old_width, (old_height * 2).min(max_size))
+        };
+        let mut image = Image::new(width, height);
+        for (dest, src) in image
+            .pixels_mut()
+            .chunks_exact_mut(width as usize * 4)
+            .zip(self.image.pixels().chunks_exact(old_width as usize * 4))
+        {
+            dest[..src.len()].copy_from_slice(src);
+        }
+        self.image = image;
+        if width > old_width {
+            self.skyline.push(Segment {
+                x: old_width,
+                y: padding.min(height),
+                width: width - old_width,
+            });
+            self.merge();
+        }
+    }
+
+    // Skyline bottom-left: picks the lowest position, then the leftmost.
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        for i in 0..self.skyline.len() {
+            let Some(y) = self.fits(i, width, height) else {
+                continue;
+            };
+            if best.is_some_and(|(_, _, best_y)| y >= best_y) {
+                continue;
+            }
+            best = Some((i, self.skyline[i].x, y));
+        }
+        let (index, x, y) = best?;
+
+        // Raises the skyline under the new rect, shortening or removing covered segments.
+        self.skyline.insert(
+            index,
+            Segment {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+        let right = x + width;
+        let next = index + 1;
+        while next < self.skyline.len() && self.skyline[next].x < right {
+            let segment = &mut self.skyline[next];
+            let segment_right = segment.x + segment.width;
+            if segment_right <= right {
+                self.skyline.remove(next);
+            } else {
+                segment.width = segment_right - right;
+                segment.x = right;
+                break;
+            }
+        }
+        self.merge();
+        Some((x, y))
+    }
+
+    // The y a rect would be placed at if its left edge starts at segment "index".
+    fn fits(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.image.width() {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for segment in &self.skyline[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment.y);
+            if y + height > self.image.height() {
+                return None;
+            }
+            remaining -= segment.width as i64;
+        }
+        Some(y)
+    }
+
+    // Joins neighbouring segments at the same height.
+    fn merge(&mut self) {
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].y == self.skyline[i + 1].y {
+                self.skyline[i].width += self.skyline[i + 1].width;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &AtlasRect, b: &AtlasRect) -> bool {
+        a.page == b.page
+            && a.x < b.x + b.width as i32
+            && b.x < a.x + a.width as i32
+            && a.y < b.y + b.height as i32
+            && b.y < a.y + a.height as i32
+    }
+
+    #[test]
+    fn packed_rects_do_not_overlap() {
+        let mut packer = AtlasPacker::new(32, 64, 1);
+        for i in 0..40 {
+            let image = Image::new(3 + i % 7, 2 + i % 5);
+            packer.add(format!("{}", i), &image).unwrap();
+        }
+        let rects: Vec<_> = packer.rects().values().collect();
+        for (i, a) in rects.iter().enumerate() {
+            let page = packer.page(a.page).unwrap();
+            assert!(a.x >= 1 && a.y >= 1);
+            assert!(a.x as u32 + a.width < page.width());
+            assert!(a.y as u32 + a.height < page.height());
+            for b in &rects[i + 1..] {
+                assert!(!overlaps(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn grows_then_adds_pages() {
+        let mut packer = AtlasPacker::new(16, 32, 0);
+        packer.add("a", &Image::new(16, 16)).unwrap();
+        packer.add("b", &Image::new(16, 16)).unwrap();
+        assert_eq!(packer.page_count(), 1);
+        assert_eq!(packer.page(0).unwrap().width(), 32);
+        packer.add("c", &Image::new(32, 16)).unwrap();
+        assert_eq!(packer.page_count(), 1);
+        packer.add("d", &Image::new(32, 32)).unwrap();
+        assert_eq!(packer.get("d").unwrap().page, 1);
+        assert!(packer.add("e", &Image::new(33, 1)).is_err());
+        assert!(packer.add("a", &Image::new(1, 1)).is_err());
+    }
+
+    #[test]
+    fn keeps_pixels_when_growing() {
+        let mut packer = AtlasPacker::new(4, 16, 0);
+        let red = Image::from_rgba(1, 1, vec![255, 0, 0, 255]).unwrap();
+        let rect = packer.add("red", &red).unwrap();
+        packer.add("big", &Image::new(8, 8)).unwrap();
+        let page = packer.page(0).unwrap();
+        assert!(page.width() > 4);
+        assert_eq!(page.get(rect.x, rect.y), red.get(0, 0));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join("mini_sdl_atlas_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("atlas.json");
+        let mut packer = AtlasPacker::new(8, 32, 1);
+        let red = Image::from_rgba(1, 1, vec![255, 0, 0, 255]).unwrap();
+        packer.add("red", &red).unwrap();
+        packer.add("block", &Image::new(5, 5)).unwrap();
+        packer.save(&path).unwrap();
+
+        let mut loaded = AtlasPacker::load(&path).unwrap();
+        assert_eq!(loaded.rects(), packer.rects());
+        assert_eq!(loaded.page(0), packer.page(0));
+        // New images must not overlap the loaded ones.
+        let new = loaded.add("new", &Image::new(4, 4)).unwrap();
+        assert!(loaded
+            .rects()
+            .values()
+            .filter(|rect| **rect != new)
+            .all(|rect| !overlaps(rect, &new)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_rejects_rects_outside_of_page() {
+        let dir = std::env::temp_dir().join("mini_sdl_atlas_bounds_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("atlas.json");
+        let mut packer = AtlasPacker::new(8, 8, 0);
+        packer.add("a", &Image::new(2, 2)).unwrap();
+        packer.save(&path).unwrap();
+
+        let sidecar = std::fs::read_to_string(&path).unwrap();
+        let mut file: AtlasFile = serde_json::from_str(&sidecar).unwrap();
+        for (x, y, width) in [(-1, 0, 2), (7, 0, 2), (0, 7, 2), (0, 0, u32::MAX)] {
+            let rect = file.rects.get_mut("a").unwrap();
+            (rect.x, rect.y, rect.width) = (x, y, width);
+            std::fs::write(&path, serde_json::to_string(&file).unwrap()).unwrap();
+            let error = AtlasPacker::load(&path).unwrap_err();
+            let error = error.downcast_ref::<std::io::Error>().unwrap();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{filter::CachedTarget, scale_rgba, Image, SdlResult};
+use sdl3::{
+    render::{Canvas, ScaleMode, Texture},
+    video::Window,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, SyncSender},
+    thread::JoinHandle,
+};
+
+#[cfg(feature = "image")]
+use std::{fs::File, io::BufWriter};
+
+// Most viewers slow down GIF frames shorter than this, in hundredths of a second.
+const MIN_GIF_DELAY: u64 = 2;
+
+// How many frames can wait for the writer thread before "frame_finish" waits for it.
+const MAX_QUEUED_FRAMES: usize = 8;
+
+// Quantization speed for "gif::Frame::from_rgba_speed", from 1 (best) to 30 (fastest).
+#[cfg(feature = "image")]
+const GIF_SPEED: i32 = 10;
+
+/// The file format of a capture started with "App::capture_start".
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum CaptureFormat {
+    #[default]
+    /// A looping animated GIF, with a palette of up to 256 colors per frame. Frames shorter
+    /// than 1/50 of a second are skipped, since most viewers can't show them. Requires the
+    /// "image" feature.
+    Gif,
+
+    /// One QOI file per frame in a folder, plus a "frames.txt" file with the name, size and
+    /// duration in seconds of each frame.
+    QoiSequence,
+
+    /// One file of uncompressed RGBA pixels per frame in a folder, plus a "frames.txt" file
+    /// with the name, size and duration in seconds of each frame.
+    RawSequence,
+}
+
+/// What is recorded on each frame.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum CaptureSource {
+    #[default]
+    /// The pixel buffer or render target at its own resolution, as last presented by
+    /// "pixel_buffer_present" or "render_target_present", or the layers composited by
+    /// "present_layers" at their presented size. Transitions and the presentation transform
+    /// are not included.
+    Frame,
+
+    /// The whole canvas at the window resolution, right before it's presented by
+    /// "frame_finish". Includes layers, transitions and the overlay.
+    Canvas,
+}
+
+/// Options for "App::capture_start".
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CaptureOptions {
+    pub format: CaptureFormat,
+    pub source: CaptureSource,
+    /// Scales each frame up by this integer factor, without filtering.
+    pub scale: u32,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            format: CaptureFormat::Gif,
+            source: CaptureSource::Frame,
+            scale: 1,
+        }
+    }
+}
+
+// A running capture. Frames are grabbed when presenting and sent on "frame_finish", so only one
+// frame is recorded per loop. Encoding and writing happen on a background thread.
+pub(crate) struct Capture {
+    options: CaptureOptions,
+    target: CachedTarget,
+    pending: Option<Image>,
+    time: f64,
+    // GIF only. The previous frame is held until the next one, to know its delay.
+    gif_last: Option<(Image, u64)>,
+    sender: Option<SyncSender<Job>>,
+    writer: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl Capture {
+    // Creates the GIF's folder, or the sequence's folder, and starts the writer thread.
+    pub(crate) fn new(path: &Path, options: CaptureOptions) -> SdlResult<Self> {
+        match options.format {
+            CaptureFormat::Gif => {
+                if cfg!(not(feature = "image")) {
+                    return Err(Box::new(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "GIF capture requires the \"image\" feature",
+                    )));
+                }
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
+                }
+            }
+            CaptureFormat::QoiSequence | CaptureFormat::RawSequence => {
+                std::fs::create_dir_all(path)?;
+            }
+        }
+        let (sender, receiver) = mpsc::sync_channel(MAX_QUEUED_FRAMES);
+        let mut frame_writer = FrameWriter::new(path, options.format);
+        let writer = std::thread::spawn(move || {
+            for job in receiver {
+                frame_writer.write(job).map_err(|error| error.to_string())?;
+            }
+            frame_writer.finish().map_err(|error| error.to_string())
+        });
+        Ok(Self {
+            options: CaptureOptions {
+                scale: options.scale.max(1),
+                ..options
+            },
+            target: CachedTarget::default(),
+            pending: None,
+            time: 0.0,
+            gif_last: None,
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    // Reads back "texture" (of "size" pixels) if capturing the presented frame.
+    pub(crate) fn grab_frame(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        texture: &mut Texture,
+        size: (u32, u32),
+    ) -> SdlResult<()> {
+        if self.options.source != CaptureSource::Frame {
+            return Ok(());
+        }
+        self.pending = Some(read_back(canvas, &mut self.target, texture, size)?);
+        Ok(())
+    }
+
+    // Sends the frame grabbed since the last call (or the canvas), lasting "elapsed_time",
+    // to the writer thread. Only waits for it if it's "MAX_QUEUED_FRAMES" behind.
+    pub(crate) fn frame_finish(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        elapsed_time: f64,
+    ) -> SdlResult<()> {
+        if self.options.source == CaptureSource::Canvas {
+            self.pending = Some(Image::from_surface(&canvas.read_pixels(None)?)?);
+        }
+        if let Some(image) = self.pending.take() {
+            let image = self.scaled(image)?;
+            match self.options.format {
+                CaptureFormat::Gif => self.send_gif(image)?,
+                CaptureFormat::QoiSequence | CaptureFormat::RawSequence => {
+                    self.send(Job::Sequence(image, elapsed_time))?
+                }
+            }
+        }
+        self.time += elapsed_time;
+        Ok(())
+    }
+
+    // Sends what's left, waits for the writer thread and frees the read back target.
+    pub(crate) fn finish(mut self) -> SdlResult<()> {
+        std::mem::take(&mut self.target).destroy();
+        if let Some((image, start)) = self.gif_last.take() {
+            let end = (self.time * 100.0).round() as u64;
+            self.send(Job::Gif(
+                image,
+                end.saturating_sub(start).max(MIN_GIF_DELAY),
+            ))?;
+        }
+        self.sender = None;
+        self.join_writer()
+    }
+
+    fn scaled(&self, image: Image) -> SdlResult<Image> {
+        let scale = self.options.scale;
+        if scale == 1 {
+            return Ok(image);
+        }
+        let (width, height) = (image.width(), image.height());
+        let pixels = scale_rgba(image.pixels(), width, height, scale)?;
+        Image::from_rgba(width * scale, height * scale, pixels)
+    }
+
+    // Holds "image" until the next frame, sending the previous one with its final delay.
+    fn send_gif(&mut self, image: Image) -> SdlResult<()> {
+        let start = (self.time * 100.0).round() as u64;
+        if let Some((last, last_start)) = self.gif_last.take() {
+            let delay = start.saturating_sub(last_start);
+            if delay < MIN_GIF_DELAY {
+                // Too short, the previous frame stays a little longer instead.
+                self.gif_last = Some((last, last_start));
+                return Ok(());
+            }
+            self.send(Job::Gif(last, delay))?;
+        }
+        self.gif_last = Some((image, start));
+        Ok(())
+    }
+
+    fn send(&mut self, job: Job) -> SdlResult<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        if sender.send(job).is_err() {
+            // The writer thread stopped early, after an error.
+            self.sender = None;
+            return self.join_writer();
+        }
+        Ok(())
+    }
+
+    fn join_writer(&mut self) -> SdlResult<()> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        match writer.join() {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(Box::new(std::io::Error::other(error))),
+            Err(_) => Err(Box::new(std::io::Error::other(
+                "Capture writer thread panicked",
+            ))),
+        }
+    }
+}
+
+// A frame for the writer thread.
+enum Job {
+    // With its delay in hundredths of a second.
+    Gif(Image, u64),
+    // With its duration in seconds.
+    Sequence(Image, f64),
+}
+
+// Encodes and writes the frames, on the writer thread.
+struct FrameWriter {
+    format: CaptureFormat,
+    path: PathBuf,
+    #[cfg(feature = "image")]
+    gif: Option<(gif::Encoder<BufWriter<File>>, u32, u32)>,
+    frame_count: usize,
+    frame_list: String,
+}
+
+impl FrameWriter {
+    fn new(path: &Path, format: CaptureFormat) -> Self {
+        Self {
+            format,
+            path: path.to_path_buf(),
+            #[cfg(feature = "image")]
+            gif: None,
+            frame_count: 0,
+            frame_list: String::new(),
+        }
+    }
+
+    fn write(&mut self, job: Job) -> SdlResult<()> {
+        match job {
+            #[cfg(feature = "image")]
+            Job::Gif(image, delay) => self.write_gif(image, delay),
+            // "Capture::new" doesn't allow GIF without the "image" feature.
+            #[cfg(not(feature = "image"))]
+            Job::Gif(..) => Ok(()),
+            Job::Sequence(image, duration) => self.write_sequence(&image, duration),
+        }
+    }
+
+    // Writes the GIF trailer, or the frame list of a sequence.
+    fn finish(self) -> SdlResult<()> {
+        #[cfg(feature = "image")]
+        if let Some((encoder, _, _)) = self.gif {
+            encoder.into_inner()?;
+        }
+        if self.frame_count > 0 && self.format != CaptureFormat::Gif {
+            std::fs::write(self.path.join("frames.txt"), &self.frame_list)?;
+        }
+        Ok(())
+    }
+
+    // Every frame gets its own palette of up to 256 colors.
+    #[cfg(feature = "image")]
+    fn write_gif(&mut self, image: Image, delay: u64) -> SdlResult<()> {
+        if self.gif.is_none() {
+            let (width, height) = (image.width(), image.height());
+            if width > u16::MAX as u32 || height > u16::MAX as u32 {
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "GIF size must be up to 65535 pixels",
+                )));
+            }
+            let file = BufWriter::new(File::create(&self.path)?);
+            let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])?;
+            encoder.set_repeat(gif::Repeat::Infinite)?;
+            self.gif = Some((encoder, width, height));
+        }
+        let (encoder, width, height) = self.gif.as_mut().unwrap();
+        let mut image = if (image.width(), image.height()) == (*width, *height) {
+            image
+        } else {
+            crop_or_pad(&image, *width, *height)
+        };
+        let mut frame = gif::Frame::from_rgba_speed(
+            *width as u16,
+            *height as u16,
+            image.pixels_mut(),
+            GIF_SPEED,
+        );
+        frame.delay = delay.min(u16::MAX as u64) as u16;
+        encoder.write_frame(&frame)?;
+        Ok(())
+    }
+
+    fn write_sequence(&mut self, image: &Image, duration: f64) -> SdlResult<()> {
+        let name = if self.format == CaptureFormat::QoiSequence {
+            let name = format!("frame_{:05}.qoi", self.frame_count);
+            std::fs::write(self.path.join(&name), image.to_qoi()?)?;
+            name
+        } else {
+            let name = format!("frame_{:05}.rgba", self.frame_count);
+            std::fs::write(self.path.join(&name), image.pixels())?;
+            name
+        };
+        self.frame_list.push_str(&format!(
+            "{} {} {} {:.6}\n",
+            name,
+            image.width(),
+            image.height(),
+            duration
+        ));
+        self.frame_count += 1;
+        Ok(())
+    }
+}
+
+// Used when the resolution changes during a capture, since every frame must have the
+// first frame's size. Pads with transparent black.
+pub(crate) fn crop_or_pad(image: &Image, width: u32, height: u32) -> Image {
+    let mut resized = Image::new(width, height);
+    resized.frame().blit_ex(image, 0, 0, &Default::default());
+    resized
+}
+
+// Copies "texture" (of "size" pixels) over black into "target" and reads it back as an image.
+pub(crate) fn read_back(
+    canvas: &mut Canvas<Window>,
+    target: &mut CachedTarget,
+    texture: &mut Texture,
+    size: (u32, u32),
+) -> SdlResult<Image> {
+    let target = target.get(canvas, size.0, size.1)?;
+    texture.set_scale_mode(ScaleMode::Nearest);
+    let mut result = Ok(());
+    let mut surface = None;
+    canvas.with_texture_canvas(target, |target| {
+        target.set_draw_color((0, 0, 0, 255));
+        target.clear();
+        result = target.copy(texture, None, None);
+        surface = Some(target.read_pixels(None));
+    })?;
+    result?;
+    match surface {
+        Some(surface) => Image::from_surface(&surface?),
+        None => Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Could not read back the frame",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mini_sdl_{}_{}", std::process::id(), name))
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn gif_frames_and_delays() {
+        let path = temp_path("capture.gif");
+        let mut writer = FrameWriter::new(&path, CaptureFormat::Gif);
+        let red = Image::from_rgba(2, 1, vec![255, 0, 0, 255, 255, 0, 0, 255]).unwrap();
+        let wide = Image::from_rgba(3, 1, [0, 0, 255, 255].repeat(3)).unwrap();
+        writer.write(Job::Gif(red, 3)).unwrap();
+        writer.write(Job::Gif(wide, 70_000)).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes.as_slice()).unwrap();
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(frame.delay, 3);
+        assert_eq!(&frame.buffer[..4], &[255, 0, 0, 255]);
+        // Cropped to the first frame's size, with the delay clamped.
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!((frame.width, frame.height, frame.delay), (2, 1, u16::MAX));
+        assert_eq!(&frame.buffer[..4], &[0, 0, 255, 255]);
+        assert!(decoder.read_next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn sequence_frame_list() {
+        let path = temp_path("sequence");
+        std::fs::create_dir_all(&path).unwrap();
+        let mut writer = FrameWriter::new(&path, CaptureFormat::RawSequence);
+        let image = Image::from_rgba(1, 1, vec![1, 2, 3, 4]).unwrap();
+        writer.write(Job::Sequence(image, 0.5)).unwrap();
+        writer.finish().unwrap();
+
+        let list = std::fs::read_to_string(path.join("frames.txt")).unwrap();
+        let pixels = std::fs::read(path.join("frame_00000.rgba")).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        assert_eq!(list, "frame_00000.rgba 1 1 0.500000\n");
+        assert_eq!(pixels, vec![1, 2, 3, 4]);
+    }
+}
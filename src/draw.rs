@@ -0,0 +1,418 @@
+// Software drawing primitives for the FrameBuffer. Everything is clipped to the buffer bounds,
+// so shapes can be partially (or completely) outside of it.
+
+use crate::FrameBuffer;
+use sdl3::pixels::Color;
+
+impl FrameBuffer<'_> {
+    /// Draws a line using Bresenham's algorithm, including both end points. Lines crossing the
+    /// buffer edges are clipped first, so only their visible part is rasterized.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        if y0 == y1 {
+            self.span(x0, x1, y0, color);
+            return;
+        }
+        let bounds = (self.width() as i64, self.height() as i64);
+        let Some((x0, y0, x1, y1)) = clip_line(x0 as i64, y0 as i64, x1 as i64, y1 as i64, bounds)
+        else {
+            return;
+        };
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            // Clipped end points are at most one pixel outside, so they fit in an i32.
+            self.set(x as i32, y as i32, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Draws the outline of a rectangle.
+    pub fn rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        // Edges outside of the buffer are moved to just outside of it, so nothing overflows
+        // and off-screen rows aren't iterated.
+        let clamp = |value: i64, max: u32| value.clamp(-1, max as i64) as i32;
+        let (left, top) = (
+            clamp(x as i64, self.width()),
+            clamp(y as i64, self.height()),
+        );
+        let right = clamp(x as i64 + width as i64 - 1, self.width());
+        let bottom = clamp(y as i64 + height as i64 - 1, self.height());
+        self.span(left, right, top, color);
+        self.span(left, right, bottom, color);
+        for row in top + 1..bottom {
+            self.set(left, row, color);
+            self.set(right, row, color);
+        }
+    }
+
+    /// Draws a filled rectangle.
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        if width == 0 {
+            return;
+        }
+        let right = (x as i64 + width as i64 - 1).min(self.width() as i64) as i32;
+        // Clipping rows here avoids iterating over huge, off-screen rectangles.
+        let top = y.max(0);
+        let bottom = (y as i64 + height as i64).min(self.height() as i64) as i32;
+        for row in top..bottom {
+            self.span(x, right, row, color);
+        }
+    }
+
+    /// Draws the outline of a circle.
+    pub fn circle(&mut self, center_x: i32, center_y: i32, radius: u32, color: Color) {
+        self.ellipse(center_x, center_y, radius, radius, color);
+    }
+
+    /// Draws a filled circle.
+    pub fn fill_circle(&mut self, center_x: i32, center_y: i32, radius: u32, color: Color) {
+        self.fill_ellipse(center_x, center_y, radius, radius, color);
+    }
+
+    /// Draws the outline of an ellipse.
+    pub fn ellipse(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        radius_x: u32,
+        radius_y: u32,
+        color: Color,
+    ) {
+        ellipse_points(radius_x, radius_y, |x, y| {
+            self.set(center_x + x, center_y + y, color);
+            self.set(center_x - x, center_y + y, color);
+            self.set(center_x + x, center_y - y, color);
+            self.set(center_x - x, center_y - y, color);
+        });
+    }
+
+    /// Draws a filled ellipse.
+    pub fn fill_ellipse(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        radius_x: u32,
+        radius_y: u32,
+        color: Color,
+    ) {
+        ellipse_points(radius_x, radius_y, |x, y| {
+            self.span(center_x - x, center_x + x, center_y + y, color);
+            self.span(center_x - x, center_x + x, center_y - y, color);
+        });
+    }
+
+    /// Draws the outline of a triangle.
+    pub fn triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: Color) {
+        self.line(a.0, a.1, b.0, b.1, color);
+        self.line(b.0, b.1, c.0, c.1, color);
+        self.line(c.0, c.1, a.0, a.1, color);
+    }
+
+    /// Draws a filled triangle.
+    pub fn fill_triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: Color) {
+        // Sorts vertices from top to bottom
+        let mut points = [a, b, c];
+        points.sort_by_key(|p| p.1);
+        let [top, mid, bottom] = points;
+
+        // Finds the x of an edge at row y, interpolating from "p0" to "p1".
+        let edge_x = |p0: (i32, i32), p1: (i32, i32), y: i32| -> i32 {
+            if p1.1 == p0.1 {
+                return p0.0;
+            }
+            let t = (y - p0.1) as f32 / (p1.1 - p0.1) as f32;
+            (p0.0 as f32 + (p1.0 - p0.0) as f32 * t).round() as i32
+        };
+
+        let first = top.1.max(0);
+        let last = bottom.1.min(self.height() as i32 - 1);
+        for y in first..=last {
+            // The long edge goes from top to bottom, the short ones meet at "mid".
+            let long = edge_x(top, bottom, y);
+            let short = if y < mid.1 {
+                edge_x(top, mid, y)
+            } else {
+                edge_x(mid, bottom, y)
+            };
+            self.span(long, short, y, color);
+        }
+    }
+
+    /// Replaces the contiguous area of same colored pixels around (x, y) with a new color.
+    pub fn flood_fill(&mut self, x: i32, y: i32, color: Color) {
+        let Some(target) = self.get(x, y) else {
+            return;
+        };
+        if target == color {
+            return;
+        }
+        // Scanline fill: fills whole horizontal runs and queues the rows above and below.
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if self.get(x, y) != Some(target) {
+                continue;
+            }
+            let mut left = x;
+            while self.get(left - 1, y) == Some(target) {
+                left -= 1;
+            }
+            let mut right = x;
+            while self.get(right + 1, y) == Some(target) {
+                right += 1;
+            }
+            self.span(left, right, y, color);
+            for row in [y - 1, y + 1] {
+                let mut in_run = false;
+                for column in left..=right {
+                    let matches = self.get(column, row) == Some(target);
+                    if matches && !in_run {
+                        stack.push((column, row));
+                    }
+                    in_run = matches;
+                }
+            }
+        }
+    }
+}
+
+// Clips a line to the buffer bounds plus a one pixel margin with the Liang-Barsky algorithm, and
+// rounds the new end points. Lines inside the margin are returned unchanged, so they're drawn
+// exactly. None if the line is completely outside.
+fn clip_line(
+    x0: i64,
+    y0: i64,
+    x1: i64,
+    y1: i64,
+    (width, height): (i64, i64),
+) -> Option<(i64, i64, i64, i64)> {
+    let (dx, dy) = ((x1 - x0) as f64, (y1 - y0) as f64);
+    // For each edge, how fast the line moves out of it and how far inside the start is.
+    let edges = [
+        (-dx, (x0 + 1) as f64),
+        (dx, (width - x0) as f64),
+        (-dy, (y0 + 1) as f64),
+        (dy, (height - y0) as f64),
+    ];
+    let (mut enter, mut exit) = (0.0f64, 1.0f64);
+    for (outward, inside) in edges {
+        if outward == 0.0 {
+            if inside < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = inside / outward;
+        if outward < 0.0 {
+            enter = enter.max(t);
+        } else {
+            exit = exit.min(t);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    let point = |t: f64| {
+        (
+            (x0 as f64 + dx * t).round() as i64,
+            (y0 as f64 + dy * t).round() as i64,
+        )
+    };
+    let (start, end) = (point(enter), point(exit));
+    Some((start.0, start.1, end.0, end.1))
+}
+
+// Midpoint ellipse algorithm. Calls "plot" for each point in the first quadrant, relative to the
+// center. The other quadrants are mirrors of it.
+fn ellipse_points(radius_x: u32, radius_y: u32, mut plot: impl FnMut(i32, i32)) {
+    let (rx, ry) = (radius_x as i64, radius_y as i64);
+    if rx == 0 || ry == 0 {
+        // Degenerates into a line
+        for x in 0..=rx {
+            plot(x as i32, 0);
+        }
+        for y in 0..=ry {
+            plot(0, y as i32);
+        }
+        return;
+    }
+    let (rx2, ry2) = (rx * rx, ry * ry);
+    let (mut x, mut y) = (0i64, ry);
+    let (mut dx, mut dy) = (0i64, 2 * rx2 * y);
+
+    // Region 1, where the slope is less than 1
+    let mut d1 = ry2 - rx2 * ry + rx2 / 4;
+    while dx < dy {
+        plot(x as i32, y as i32);
+        x += 1;
+        dx += 2 * ry2;
+        if d1 < 0 {
+            d1 += dx + ry2;
+        } else {
+            y -= 1;
+            dy -= 2 * rx2;
+            d1 += dx - dy + ry2;
+        }
+    }
+
+    // Region 2
+    let mut d2 = ry2 * (2 * x + 1) * (2 * x + 1) / 4 + rx2 * (y - 1) * (y - 1) - rx2 * ry2;
+    while y >= 0 {
+        plot(x as i32, y as i32);
+        y -= 1;
+        dy -= 2 * rx2;
+        if d2 > 0 {
+            d2 += rx2 - dy;
+        } else {
+            x += 1;
+            dx += 2 * ry2;
+            d2 += dx - dy + rx2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FrameBuffer, PixelFormat};
+    use sdl3::pixels::Color;
+
+    const W: u32 = 16;
+    const H: u32 = 16;
+
+    fn buffer(bytes: &mut [u8]) -> FrameBuffer<'_> {
+        FrameBuffer::new(bytes, W as usize * 3, W, H, PixelFormat::RGB24)
+    }
+
+    fn count(buffer: &FrameBuffer, color: Color) -> usize {
+        buffer.pixels().filter(|(_, _, c)| *c == color).count()
+    }
+
+    #[test]
+    fn line_includes_end_points() {
+        let mut bytes = vec![0; (W * H * 3) as usize];
+        let mut fb = buffer(&mut bytes);
+        fb.line(1, 1, 10, 5, Color::WHITE);
+        assert_eq!(fb.get(1, 1), Some(Color::WHITE));
+        assert_eq!(fb.get(10, 5), Some(Color::WHITE));
+        assert_eq!(count(&fb, Color::WHITE), 10);
+    }
+
+    #[test]
+    fn shapes_are_clipped() {
+        let mut bytes = vec![0; (W * H * 3) as usize];
+        let mut fb = buffer(&mut bytes);
+        fb.fill_rect(-100, -100, 1000, 1000, Color::WHITE);
+        assert_eq!(count(&fb, Color::WHITE), (W * H) as usize);
+        fb.clear();
+
+        fb.fill_rect(12, -4, 10, 8, Color::WHITE);
+        assert_eq!(fb.get(12, 0), Some(Color::WHITE));
+        assert_eq!(fb.get(15, 3), Some(Color::WHITE));
+        assert_eq!(fb.get(11, 0), Some(Color::BLACK));
+        assert_eq!(fb.get(12, 4), Some(Color::BLACK));
+
+        fb.fill_circle(0, 0, 5, Color::RED);
+        assert_eq!(fb.get(0, 0), Some(Color::RED));
+        assert_eq!(fb.get(5, 0), Some(Color::RED));
+        assert_eq!(fb.get(0, 5), Some(Color::RED));
+        assert_eq!(fb.get(4, 4), Some(Color::BLACK));
+        assert_eq!(fb.get(6, 0), Some(Color::BLACK));
+
+        fb.line(-50, 8, 50, 8, Color::BLUE);
+        assert_eq!(fb.get(0, 8), Some(Color::BLUE));
+        assert_eq!(fb.get(15, 8), Some(Color::BLUE));
+        assert_eq!(fb.get(0, 7), Some(Color::BLACK));
+        assert_eq!(fb.get(15, 9), Some(Color::BLACK));
+
+        // Crosses the left and bottom edges.
+        fb.fill_triangle((-10, 10), (10, 10), (0, 30), Color::GREEN);
+        assert_eq!(fb.get(0, 10), Some(Color::GREEN));
+        assert_eq!(fb.get(10, 10), Some(Color::GREEN));
+        assert_eq!(fb.get(11, 10), Some(Color::BLACK));
+        assert_eq!(fb.get(0, 15), Some(Color::GREEN));
+        assert_eq!(fb.get(8, 15), Some(Color::GREEN));
+        assert_eq!(fb.get(9, 15), Some(Color::BLACK));
+        assert_eq!(count(&fb, Color::WHITE), 16);
+    }
+
+    #[test]
+    fn extreme_coordinates() {
+        let mut bytes = vec![0; (W * H * 3) as usize];
+        let mut fb = buffer(&mut bytes);
+        // Would take billions of steps without clipping.
+        fb.line(-1_000_000_000, 0, 1_000_000_000, 1, Color::WHITE);
+        assert_eq!(count(&fb, Color::WHITE), W as usize);
+        fb.clear();
+
+        fb.line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, Color::WHITE);
+        assert_eq!(fb.get(0, 0), Some(Color::WHITE));
+        assert_eq!(fb.get(15, 15), Some(Color::WHITE));
+        assert_eq!(count(&fb, Color::WHITE), W as usize);
+        fb.clear();
+
+        fb.rect(i32::MIN, 4, u32::MAX, 4, Color::WHITE);
+        assert_eq!(fb.get(15, 4), Some(Color::WHITE));
+        assert_eq!(fb.get(15, 5), Some(Color::BLACK));
+        fb.clear();
+        fb.rect(i32::MAX, i32::MAX, u32::MAX, u32::MAX, Color::WHITE);
+        fb.fill_rect(i32::MAX, 0, u32::MAX, u32::MAX, Color::WHITE);
+        assert_eq!(count(&fb, Color::WHITE), 0);
+        fb.fill_rect(i32::MIN, i32::MIN, 1000, 1000, Color::WHITE);
+        assert_eq!(count(&fb, Color::WHITE), 0);
+        fb.fill_rect(-4, -4, u32::MAX, u32::MAX, Color::WHITE);
+        assert_eq!(count(&fb, Color::WHITE), (W * H) as usize);
+    }
+
+    #[test]
+    fn rect_outline() {
+        let mut bytes = vec![0; (W * H * 3) as usize];
+        let mut fb = buffer(&mut bytes);
+        fb.rect(2, 2, 4, 3, Color::WHITE);
+        // 4 + 4 on top and bottom, 1 + 1 on the middle row
+        assert_eq!(count(&fb, Color::WHITE), 10);
+        assert_eq!(fb.get(3, 3), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn flood_fill_stops_at_borders() {
+        let mut bytes = vec![0; (W * H * 3) as usize];
+        let mut fb = buffer(&mut bytes);
+        fb.rect(2, 2, 6, 6, Color::WHITE);
+        fb.flood_fill(4, 4, Color::RED);
+        assert_eq!(count(&fb, Color::RED), 16);
+        fb.flood_fill(0, 0, Color::BLUE);
+        assert_eq!(count(&fb, Color::BLUE), (W * H) as usize - 36);
+    }
+
+    #[test]
+    fn fill_circle_is_symmetric() {
+        let mut bytes = vec![0; (W * H * 3) as usize];
+        let mut fb = buffer(&mut bytes);
+        fb.fill_circle(8, 8, 5, Color::WHITE);
+        for (x, y, color) in fb.pixels() {
+            let mirrored = fb.get(16 - x as i32, y as i32);
+            if x > 0 {
+                assert_eq!(Some(color), mirrored);
+            }
+        }
+        assert_eq!(fb.get(8, 3), Some(Color::WHITE));
+        assert_eq!(fb.get(8, 2), Some(Color::BLACK));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{Scaling, SdlResult, Transform};
+use sdl3::{
+    pixels::PixelFormat,
+    render::{BlendMode, Canvas, FRect, ScaleMode, Texture},
+    sys::pixels::SDL_PixelFormat,
+    video::Window,
+};
+
+/// The texture filter used when presenting the pixel buffer or render target.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum Filter {
+    #[default]
+    /// Sharp pixels. Best with Scaling::Integer, since non-integer scales
+    /// produce pixels of uneven sizes.
+    Nearest,
+
+    /// Smooth, blurry results. Not recommended for pixel art.
+    Linear,
+
+    /// Scales the image to the nearest integer multiple using "Nearest", then to the final size
+    /// using "Linear". Pixels stay sharp and evenly sized at any scale, with a tiny bit of
+    /// smoothing only at their edges. Best with Scaling::PreserveAspect.
+    SharpBilinear,
+}
+
+/// The texture filter used with each scaling strategy when presenting the pixel buffer or
+/// render target. The defaults keep pixels sharp: "Nearest" at integer scales and
+/// "SharpBilinear" where the scale can be fractional.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScalingFilters {
+    pub integer: Filter,
+    pub preserve_aspect: Filter,
+    pub stretch_to_window: Filter,
+    pub expand: Filter,
+}
+
+impl Default for ScalingFilters {
+    fn default() -> Self {
+        Self {
+            integer: Filter::Nearest,
+            preserve_aspect: Filter::SharpBilinear,
+            stretch_to_window: Filter::SharpBilinear,
+            expand: Filter::Nearest,
+        }
+    }
+}
+
+impl ScalingFilters {
+    /// Sets the same filter for every scaling strategy.
+    pub fn all(filter: Filter) -> Self {
+        Self {
+            integer: filter,
+            preserve_aspect: filter,
+            stretch_to_window: filter,
+            expand: filter,
+        }
+    }
+
+    /// The filter used with "scaling".
+    pub fn get(&self, scaling: &Scaling) -> Filter {
+        match scaling {
+            Scaling::Integer => self.integer,
+            Scaling::PreserveAspect => self.preserve_aspect,
+            Scaling::StretchToWindow => self.stretch_to_window,
+            Scaling::Expand { .. } => self.expand,
+        }
+    }
+}
+
+// An intermediate render target reused across frames and only recreated when its size changes.
+// Used by Filter::SharpBilinear and the letterbox blur.
+#[derive(Default)]
+pub(crate) struct CachedTarget {
+    texture: Option<Texture>,
+    width: u32,
+    height: u32,
+}
+
+impl CachedTarget {
+    // Returns the texture, recreating it if its size doesn't match.
+    pub(crate) fn get(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        width: u32,
+        height: u32,
+    ) -> SdlResult<&mut Texture> {
+        if self.texture.is_none() || width != self.width || height != self.height {
+            if let Some(texture) = self.texture.take() {
+                // Required with "unsafe_textures".
+                unsafe { texture.destroy() };
+            }
+            let mut texture = canvas.create_texture_target(
+                unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGBA32) },
+                width,
+                height,
+            )?;
+            texture.set_scale_mode(ScaleMode::Linear);
+            self.texture = Some(texture);
+            self.width = width;
+            self.height = height;
+        }
+        Ok(self.texture.as_mut().unwrap())
+    }
+
+    // The texture, if it was created.
+    pub(crate) fn texture(&mut self) -> Option<&mut Texture> {
+        self.texture.as_mut()
+    }
+
+    // The size of the texture, if it was created.
+    pub(crate) fn size(&self) -> Option<(u32, u32)> {
+        self.texture.as_ref().map(|_| (self.width, self.height))
+    }
+
+    // Frees the texture, if any. Required with "unsafe_textures".
+    pub(crate) fn destroy(self) {
+        if let Some(texture) = self.texture {
+            unsafe { texture.destroy() };
+        }
+    }
+}
+
+// Copies "texture" (of "source_size" pixels) to "dest" in the canvas using the provided filter.
+// "dest" already includes the offset and zoom of "transform", which adds rotation and flips.
+pub(crate) fn copy_filtered(
+    canvas: &mut Canvas<Window>,
+    texture: &mut Texture,
+    source_size: (u32, u32),
+    dest: FRect,
+    filter: Filter,
+    prescaler: &mut CachedTarget,
+    transform: &Transform,
+) -> SdlResult<()> {
+    let texture = match filter {
+        Filter::Nearest | Filter::Linear => {
+            texture.set_scale_mode(match filter {
+                Filter::Linear => ScaleMode::Linear,
+                _ => ScaleMode::Nearest,
+            });
+            texture
+        }
+        Filter::SharpBilinear => {
+            let dest_size = (dest.w.round() as u32, dest.h.round() as u32);
+            prescale(canvas, texture, source_size, dest_size, prescaler)?
+        }
+    };
+    transform.copy(canvas, texture, None, dest, dest)
+}
+
+// Scales "texture" (of "source_size" pixels) by the smallest integer factor that covers
+// "dest_size", using "Nearest". Returns the intermediate texture, which uses "Linear".
+pub(crate) fn prescale<'a>(
+    canvas: &mut Canvas<Window>,
+    texture: &mut Texture,
+    source_size: (u32, u32),
+    dest_size: (u32, u32),
+    prescaler: &'a mut CachedTarget,
+) -> SdlResult<&'a mut Texture> {
+    texture.set_scale_mode(ScaleMode::Nearest);
+    let scale_x = dest_size.0.div_ceil(source_size.0);
+    let scale_y = dest_size.1.div_ceil(source_size.1);
+    let scale = scale_x.max(scale_y).max(1);
+    let intermediate = prescaler.get(canvas, source_size.0 * scale, source_size.1 * scale)?;
+    // Copies alpha as is, so it's only blended once when the intermediate is drawn.
+    let blend_mode = texture.blend_mode();
+    texture.set_blend_mode(BlendMode::None);
+    let mut result = Ok(());
+    canvas.with_texture_canvas(intermediate, |target| {
+        target.set_draw_color((0, 0, 0, 0));
+        target.clear();
+        result = target.copy(texture, None, None);
+    })?;
+    texture.set_blend_mode(blend_mode);
+    result?;
+    Ok(intermediate)
+}
@@ -1,10 +1,8 @@
-use crate::{next_power_of_two, SdlResult};
+use crate::{AtlasPacker, BlitOptions, Image, SdlResult};
 use sdl3::{
-    pixels::{Color, PixelFormat},
-    rect::Rect,
+    pixels::Color,
+    rect::{Point, Rect},
     render::{Canvas, Texture, TextureCreator},
-    surface::Surface,
-    sys::pixels::SDL_PixelFormat,
     ttf::Sdl3TtfContext,
     video::{Window, WindowContext},
 };
@@ -52,50 +50,36 @@ impl FontAtlas {
             // println!("{}: {:?}, {:?}", ch, rect, m);
         }
 
-        // Render individual surfaces for each character
-        let mut pixel_count = 0;
-        let mut surfaces = vec![];
-        for ch in CHARACTERS.chars() {
+        // Render each character and pack them into a single atlas page
+        let mut packer = AtlasPacker::new(128, 4096, 1);
+        for (ch, char_rect) in chars.iter().zip(&char_rects) {
             let surf = ttf_font
-                .render_char(ch)
+                .render_char(*ch)
                 // .lcd(Color::RGBA(255, 255, 255, 255), Color::RGBA(0, 0, 0, 255))?;
                 .blended(Color::RGBA(255, 255, 255, 255))?;
-            pixel_count += surf.width() * surf.height();
-            surfaces.push(surf);
+            // The drawn area spans the advance and the full line height, which can differ
+            // from the rendered surface.
+            let mut cell = Image::new(char_rect.width(), char_rect.height() + char_rect.y as u32);
+            cell.frame()
+                .blit_ex(&Image::from_surface(&surf)?, 0, 0, &BlitOptions::default());
+            packer.add(ch.to_string(), &cell)?;
         }
-
-        // Combine all character surfaces into a single atlas surface
-        let res = next_power_of_two((pixel_count as f32).sqrt().ceil() as u32);
-        let mut atlas = Surface::new(res, res, unsafe {
-            PixelFormat::from_ll(SDL_PixelFormat::RGBA32)
-        })?;
-        let mut rects = HashMap::new();
-        let mut row_height = 0;
-        let mut x = 0;
-        let mut y = 0;
-        for (i, surf) in &mut surfaces.iter().enumerate() {
-            if x + surf.width() as i32 >= res as i32 {
-                x = 0;
-                y += row_height;
-                row_height = 0;
-            }
-            let Some(dst_rect) = char_rects.get(i) else {
-                continue;
-            };
-            let new_rect = Rect::new(
-                x,
-                y,
-                dst_rect.width(),
-                dst_rect.height() + dst_rect.y as u32,
-            );
-            surf.blit(None, &mut atlas, new_rect)?;
-            row_height = row_height.max(surf.height() as i32);
-            x += surf.width() as i32;
-            rects.insert(chars[i], new_rect);
+        if packer.page_count() > 1 {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Font size is too large to fit a single atlas texture",
+            )));
         }
+        let rects: HashMap<char, Rect> = chars
+            .iter()
+            .filter_map(|ch| Some((*ch, packer.get(&ch.to_string())?.rect())))
+            .collect();
 
-        // Generate texture from surface
-        let texture = texture_creator.create_texture_from_surface(&atlas)?;
+        // Generate texture from the packed page
+        let texture = match packer.page(0) {
+            Some(page) => page.to_texture(texture_creator)?,
+            None => Image::new(1, 1).to_texture(texture_creator)?,
+        };
         // texture.set_scale_mode(ScaleMode::Nearest);
 
         // Finish
@@ -141,3 +125,21 @@ impl FontAtlas {
         Ok(())
     }
 }
+
+// Draws and clears the overlay lines, one below the other.
+pub(crate) fn draw_overlay(
+    font: &mut FontAtlas,
+    lines: &mut Vec<String>,
+    coords: Point,
+    scale: f32,
+    line_spacing: f32,
+    canvas: &mut Canvas<Window>,
+) -> SdlResult<()> {
+    let mut y = coords.y;
+    for line in lines.drain(..) {
+        font.draw(line, coords.x, y, scale, canvas)?;
+        let inc = (font.height() as f32 * line_spacing) * scale;
+        y += (inc * font.line_spacing) as i32;
+    }
+    Ok(())
+}
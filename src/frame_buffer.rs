@@ -0,0 +1,207 @@
+use crate::PixelFormat;
+use sdl3::pixels::Color;
+
+/// A view into locked pixels that knows their size, format and pitch (the length in bytes of a
+/// row, which may include padding). Provides bounds checked access, so coordinates outside
+/// the buffer are simply ignored.
+/// Obtain one with "App::pixel_buffer_frame", or wrap the raw slice inside "pixel_buffer_update"
+/// with "FrameBuffer::new". The raw bytes are still available for hot loops.
+pub struct FrameBuffer<'a> {
+    pixels: &'a mut [u8],
+    pitch: usize,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+}
+
+impl<'a> FrameBuffer<'a> {
+    /// Wraps raw pixel bytes. Panics if "pixels" is too small for the size, format and pitch.
+    pub fn new(
+        pixels: &'a mut [u8],
+        pitch: usize,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        assert!(pitch >= width * format.bytes_per_pixel());
+        if height > 0 {
+            assert!(pixels.len() >= pitch * (height - 1) + width * format.bytes_per_pixel());
+        }
+        Self {
+            pixels,
+            pitch,
+            width,
+            height,
+            format,
+        }
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    /// Length in bytes of a row of pixels, including any padding.
+    pub fn pitch(&self) -> usize {
+        self.pitch
+    }
+
+    /// The format of the pixels.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    #[inline(always)]
+    fn offset(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(y as usize * self.pitch + x as usize * self.format.bytes_per_pixel())
+    }
+
+    /// Sets a pixel. Does nothing if the coordinates are outside the buffer.
+    #[inline(always)]
+    pub fn set(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(i) = self.offset(x, y) {
+            self.format.write(color, &mut self.pixels[i..]);
+        }
+    }
+
+    /// Returns a pixel's color, or None if the coordinates are outside the buffer.
+    #[inline(always)]
+    pub fn get(&self, x: i32, y: i32) -> Option<Color> {
+        self.offset(x, y)
+            .map(|i| self.format.read(&self.pixels[i..]))
+    }
+
+    /// The bytes of a row of pixels, without the padding. Panics if "y" is out of bounds.
+    pub fn row_mut(&mut self, y: u32) -> &mut [u8] {
+        let start = y as usize * self.pitch;
+        let len = self.width * self.format.bytes_per_pixel();
+        &mut self.pixels[start..start + len]
+    }
+
+    /// Iterates over the rows of pixels, without the padding.
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> + '_ {
+        let len = self.width * self.format.bytes_per_pixel();
+        self.pixels
+            .chunks_mut(self.pitch)
+            .take(self.height)
+            .map(move |row| &mut row[..len])
+    }
+
+    /// Iterates over all pixels, left to right and top to bottom, as (x, y, color).
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32, Color)> + '_ {
+        let bpp = self.format.bytes_per_pixel();
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).map(move |x| {
+                let i = y * self.pitch + x * bpp;
+                (x as u32, y as u32, self.format.read(&self.pixels[i..]))
+            })
+        })
+    }
+
+    /// Fills the entire buffer with a color.
+    pub fn fill(&mut self, color: Color) {
+        let bpp = self.format.bytes_per_pixel();
+        // Encodes the color once, then copies it.
+        let mut encoded = [0u8; 4];
+        self.format.write(color, &mut encoded);
+        for row in self.rows_mut() {
+            for pixel in row.chunks_exact_mut(bpp) {
+                pixel.copy_from_slice(&encoded[..bpp]);
+            }
+        }
+    }
+
+    // Sets a horizontal line of pixels from x0 to x1 (inclusive), clipped to the buffer.
+    pub(crate) fn span(&mut self, x0: i32, x1: i32, y: i32, color: Color) {
+        let (x0, x1) = (x0.min(x1).max(0), x0.max(x1).min(self.width as i32 - 1));
+        if y < 0 || y as usize >= self.height || x0 > x1 {
+            return;
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let mut encoded = [0u8; 4];
+        self.format.write(color, &mut encoded);
+        let start = y as usize * self.pitch + x0 as usize * bpp;
+        let end = y as usize * self.pitch + (x1 as usize + 1) * bpp;
+        for pixel in self.pixels[start..end].chunks_exact_mut(bpp) {
+            pixel.copy_from_slice(&encoded[..bpp]);
+        }
+    }
+
+    /// Sets all bytes to zero, which is black (and transparent, in formats with alpha).
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    /// The raw bytes, including row padding.
+    pub fn raw(&self) -> &[u8] {
+        self.pixels
+    }
+
+    /// The raw bytes, including row padding.
+    pub fn raw_mut(&mut self) -> &mut [u8] {
+        self.pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get_all_formats() {
+        let formats = [
+            PixelFormat::RGB24,
+            PixelFormat::RGBA32,
+            PixelFormat::ARGB8888,
+            PixelFormat::XRGB8888,
+        ];
+        for format in formats {
+            let mut bytes = vec![0u8; 4 * 4 * 4];
+            let mut buffer = FrameBuffer::new(&mut bytes, 16, 4, 4, format);
+            let color = Color::RGBA(10, 20, 30, 255);
+            buffer.set(2, 3, color);
+            assert_eq!(buffer.get(2, 3), Some(color));
+            // Formats without alpha are always opaque.
+            let alpha = if format.has_alpha() { 0 } else { 255 };
+            assert_eq!(buffer.get(1, 3), Some(Color::RGBA(0, 0, 0, alpha)));
+        }
+    }
+
+    #[test]
+    fn rgb565_keeps_white() {
+        let mut bytes = vec![0u8; 8];
+        let mut buffer = FrameBuffer::new(&mut bytes, 4, 2, 2, PixelFormat::RGB565);
+        buffer.set(1, 1, Color::WHITE);
+        assert_eq!(buffer.get(1, 1), Some(Color::WHITE));
+    }
+
+    #[test]
+    fn out_of_bounds_is_ignored() {
+        let mut bytes = vec![0u8; 2 * 2 * 3];
+        let mut buffer = FrameBuffer::new(&mut bytes, 6, 2, 2, PixelFormat::RGB24);
+        buffer.set(-1, 0, Color::WHITE);
+        buffer.set(2, 0, Color::WHITE);
+        buffer.set(0, 2, Color::WHITE);
+        assert_eq!(buffer.get(2, 0), None);
+        assert!(bytes.iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn fill_skips_padding() {
+        // 2 pixels per row, plus 2 bytes of padding.
+        let mut bytes = vec![0u8; 8 * 2];
+        let mut buffer = FrameBuffer::new(&mut bytes, 8, 2, 2, PixelFormat::RGB24);
+        buffer.fill(Color::RGB(1, 2, 3));
+        assert_eq!(buffer.pixels().count(), 4);
+        assert_eq!(&bytes[..8], &[1, 2, 3, 1, 2, 3, 0, 0]);
+    }
+}
@@ -0,0 +1,576 @@
+use crate::{FrameBuffer, PixelFormat, SdlResult};
+use sdl3::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Texture, TextureCreator},
+    surface::Surface,
+};
+use std::path::Path;
+
+#[cfg(feature = "image")]
+const UNSUPPORTED_FORMAT: &str = "Unsupported image format, use PNG, QOI, BMP, JPEG or GIF";
+#[cfg(not(feature = "image"))]
+const UNSUPPORTED_FORMAT: &str =
+    "Unsupported image format, use PNG, QOI or BMP (JPEG and GIF require the \"image\" feature)";
+
+/// An image stored in CPU memory as RGBA pixels, 4 bytes per pixel, that can be drawn
+/// into the pixel buffer with "FrameBuffer::blit". Can be loaded from PNG, QOI and BMP files,
+/// plus JPEG and GIF with the "image" feature, and saved as PNG or QOI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// A new, fully transparent image. Panics if its size in bytes doesn't fit in a usize.
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = rgba_len(width, height).expect("Image size overflows usize");
+        Self {
+            width,
+            height,
+            pixels: vec![0; len],
+        }
+    }
+
+    /// Creates an image from RGBA pixels, 4 bytes per pixel.
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> SdlResult<Self> {
+        if rgba_len(width, height).ok() != Some(pixels.len()) {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "RGBA data length must be width * height * 4",
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Loads a PNG, QOI or BMP file, depending on its extension. JPEG and GIF files are
+    /// also supported with the "image" feature.
+    pub fn load(path: impl AsRef<Path>) -> SdlResult<Self> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some("png") => Self::from_png(&std::fs::read(path)?),
+            Some("qoi") => Self::from_qoi(&std::fs::read(path)?),
+            Some("bmp") => Self::load_bmp(path),
+            #[cfg(feature = "image")]
+            Some("jpg" | "jpeg") => Self::from_jpeg(&std::fs::read(path)?),
+            #[cfg(feature = "image")]
+            Some("gif") => Self::from_gif(&std::fs::read(path)?),
+            _ => Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                UNSUPPORTED_FORMAT,
+            ))),
+        }
+    }
+
+    /// Saves the image as PNG or QOI, depending on the extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> SdlResult<()> {
+        let path = path.as_ref();
+        let bytes = match extension(path).as_deref() {
+            Some("png") => self.to_png()?,
+            Some("qoi") => self.to_qoi()?,
+            _ => {
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Unsupported image format, use PNG or QOI",
+                )))
+            }
+        };
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Encodes the image as an RGBA PNG file in memory.
+    pub fn to_png(&self) -> SdlResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+
+    /// Encodes the image as an RGBA QOI file in memory.
+    pub fn to_qoi(&self) -> SdlResult<Vec<u8>> {
+        Ok(qoi::encode_to_vec(&self.pixels, self.width, self.height)?)
+    }
+
+    /// Decodes a PNG file from memory.
+    pub fn from_png(bytes: &[u8]) -> SdlResult<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+        // Expands palettes and low bit depths, and strips 16 bit channels down to 8.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            png::ColorType::Indexed => unreachable!("Expanded by normalize_to_color8"),
+        };
+        let mut pixels = Vec::with_capacity(rgba_len(info.width, info.height)?);
+        for row in buffer
+            .chunks_exact(info.line_size)
+            .take(info.height as usize)
+        {
+            for pixel in row[..info.width as usize * channels].chunks_exact(channels) {
+                let rgba = match channels {
+                    1 => [pixel[0], pixel[0], pixel[0], 255],
+                    2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    3 => [pixel[0], pixel[1], pixel[2], 255],
+                    _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                };
+                pixels.extend_from_slice(&rgba);
+            }
+        }
+        Self::from_rgba(info.width, info.height, pixels)
+    }
+
+    /// Decodes a QOI file from memory.
+    pub fn from_qoi(bytes: &[u8]) -> SdlResult<Self> {
+        let mut decoder = qoi::Decoder::new(bytes)?.with_channels(qoi::Channels::Rgba);
+        let (width, height) = (decoder.header().width, decoder.header().height);
+        let pixels = decoder.decode_to_vec()?;
+        Self::from_rgba(width, height, pixels)
+    }
+
+    /// Decodes a JPEG file from memory.
+    #[cfg(feature = "image")]
+    pub fn from_jpeg(bytes: &[u8]) -> SdlResult<Self> {
+        let mut decoder = jpeg_decoder::Decoder::new(bytes);
+        let buffer = decoder.decode()?;
+        let info = decoder.info().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing JPEG header")
+        })?;
+        let (width, height) = (info.width as u32, info.height as u32);
+        let mut pixels = Vec::with_capacity(rgba_len(width, height)?);
+        match info.pixel_format {
+            jpeg_decoder::PixelFormat::L8 => {
+                for &l in &buffer {
+                    pixels.extend_from_slice(&[l, l, l, 255]);
+                }
+            }
+            // Big endian, only the most significant byte is kept.
+            jpeg_decoder::PixelFormat::L16 => {
+                for l in buffer.chunks_exact(2) {
+                    pixels.extend_from_slice(&[l[0], l[0], l[0], 255]);
+                }
+            }
+            jpeg_decoder::PixelFormat::RGB24 => {
+                for rgb in buffer.chunks_exact(3) {
+                    pixels.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+                }
+            }
+            // Adobe JPEGs store CMYK inverted.
+            jpeg_decoder::PixelFormat::CMYK32 => {
+                for cmyk in buffer.chunks_exact(4) {
+                    let k = cmyk[3] as u16;
+                    let channel = |c: u8| (c as u16 * k / 255) as u8;
+                    pixels.extend_from_slice(&[
+                        channel(cmyk[0]),
+                        channel(cmyk[1]),
+                        channel(cmyk[2]),
+                        255,
+                    ]);
+                }
+            }
+        }
+        Self::from_rgba(width, height, pixels)
+    }
+
+    /// Decodes the first frame of a GIF file from memory. Pixels outside of the frame are
+    /// transparent.
+    #[cfg(feature = "image")]
+    pub fn from_gif(bytes: &[u8]) -> SdlResult<Self> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes)?;
+        let (width, height) = (decoder.width() as u32, decoder.height() as u32);
+        let mut image = Self {
+            width,
+            height,
+            pixels: vec![0; rgba_len(width, height)?],
+        };
+        let frame = decoder.read_next_frame()?.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "GIF file has no frames")
+        })?;
+        if frame.width == 0 || frame.height == 0 {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "GIF frame has a width or height of zero",
+            )));
+        }
+        let (left, top) = (frame.left as usize, frame.top as usize);
+        let frame_width = frame.width as usize;
+        for (y, row) in frame.buffer.chunks_exact(frame_width * 4).enumerate() {
+            let y = top + y;
+            if y >= image.height as usize || left >= image.width as usize {
+                break;
+            }
+            let width = frame_width.min(image.width as usize - left);
+            let start = (y * image.width as usize + left) * 4;
+            image.pixels[start..start + width * 4].copy_from_slice(&row[..width * 4]);
+        }
+        Ok(image)
+    }
+
+    /// Loads a BMP file using SDL.
+    pub fn load_bmp(path: impl AsRef<Path>) -> SdlResult<Self> {
+        Self::from_surface(&Surface::load_bmp(path)?)
+    }
+
+    /// Copies the pixels of an SDL surface of any format.
+    pub fn from_surface(surface: &Surface) -> SdlResult<Self> {
+        let surface = surface.convert_format(PixelFormat::RGBA32.to_sdl())?;
+        let (width, height, pitch) = (surface.width(), surface.height(), surface.pitch());
+        let mut pixels = Vec::with_capacity(rgba_len(width, height)?);
+        surface.with_lock(|bytes: &[u8]| {
+            for row in bytes.chunks(pitch as usize).take(height as usize) {
+                pixels.extend_from_slice(&row[..width as usize * 4]);
+            }
+        });
+        Self::from_rgba(width, height, pixels)
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA pixels, 4 bytes per pixel.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// The RGBA pixels, 4 bytes per pixel.
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Creates a static SDL texture with alpha blending enabled from this image.
+    pub fn to_texture<T>(&self, texture_creator: &TextureCreator<T>) -> SdlResult<Texture> {
+        let mut texture = texture_creator.create_texture_static(
+            PixelFormat::RGBA32.to_sdl(),
+            self.width,
+            self.height,
+        )?;
+        texture.update(None, &self.pixels, self.width as usize * 4)?;
+        texture.set_blend_mode(BlendMode::Blend);
+        Ok(texture)
+    }
+
+    /// A FrameBuffer view of this image, to draw into it with the same functions as the
+    /// pixel buffer.
+    pub fn frame(&mut self) -> FrameBuffer<'_> {
+        FrameBuffer::new(
+            &mut self.pixels,
+            self.width as usize * 4,
+            self.width,
+            self.height,
+            PixelFormat::RGBA32,
+        )
+    }
+
+    /// Returns a pixel's color, or None if the coordinates are outside the image.
+    pub fn get(&self, x: i32, y: i32) -> Option<Color> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let p = &self.pixels[i..i + 4];
+        Some(Color::RGBA(p[0], p[1], p[2], p[3]))
+    }
+
+    /// Sets a pixel. Does nothing if the coordinates are outside the image.
+    pub fn set(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+}
+
+/// Options for "FrameBuffer::blit_ex".
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BlitOptions {
+    /// The area of the image to draw. The whole image is drawn if None.
+    pub source: Option<Rect>,
+    /// Mirrors the image horizontally.
+    pub flip_x: bool,
+    /// Mirrors the image vertically.
+    pub flip_y: bool,
+    /// Pixels with this RGB color (alpha is ignored) are skipped.
+    pub color_key: Option<Color>,
+    /// Blends pixels with the destination using their alpha. If false, alpha is copied as is,
+    /// which is faster.
+    pub blend: bool,
+}
+
+impl FrameBuffer<'_> {
+    /// Draws an image with alpha blending, its top-left corner at (x, y). Clipped to the buffer.
+    pub fn blit(&mut self, image: &Image, x: i32, y: i32) {
+        self.blit_ex(
+            image,
+            x,
+            y,
+            &BlitOptions {
+                blend: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Draws an image using the provided options, its top-left corner at (x, y).
+    /// Clipped to the buffer.
+    pub fn blit_ex(&mut self, image: &Image, x: i32, y: i32, options: &BlitOptions) {
+        // Source rect, clipped to the image
+        let (src_x, src_y, src_w, src_h) = match options.source {
+            Some(rect) => {
+                let left = rect.x().max(0);
+                let top = rect.y().max(0);
+                let right = (rect.x() + rect.width() as i32).min(image.width as i32);
+                let bottom = (rect.y() + rect.height() as i32).min(image.height as i32);
+                (left, top, right - left, bottom - top)
+            }
+            None => (0, 0, image.width as i32, image.height as i32),
+        };
+        if src_w <= 0 || src_h <= 0 {
+            return;
+        }
+
+        // Only iterates over the visible part
+        let first_x = (-x).max(0);
+        let first_y = (-y).max(0);
+        let last_x = src_w.min(self.width() as i32 - x);
+        let last_y = src_h.min(self.height() as i32 - y);
+
+        for row in first_y..last_y {
+            let image_y = if options.flip_y {
+                src_y + src_h - 1 - row
+            } else {
+                src_y + row
+            };
+            for column in first_x..last_x {
+                let image_x = if options.flip_x {
+                    src_x + src_w - 1 - column
+                } else {
+                    src_x + column
+                };
+                let Some(color) = image.get(image_x, image_y) else {
+                    continue;
+                };
+                if let Some(key) = options.color_key {
+                    if (key.r, key.g, key.b) == (color.r, color.g, color.b) {
+                        continue;
+                    }
+                }
+                let (dest_x, dest_y) = (x + column, y + row);
+                if options.blend && color.a < 255 {
+                    if color.a == 0 {
+                        continue;
+                    }
+                    if let Some(dest) = self.get(dest_x, dest_y) {
+                        self.set(dest_x, dest_y, blend(color, dest));
+                    }
+                } else {
+                    self.set(dest_x, dest_y, color);
+                }
+            }
+        }
+    }
+}
+
+// The size in bytes of "width" x "height" RGBA pixels. Fails instead of overflowing, since
+// decoded sizes come from untrusted headers.
+fn rgba_len(width: u32, height: u32) -> SdlResult<usize> {
+    let len = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|len| len.checked_mul(4));
+    len.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "Image size is too large").into()
+    })
+}
+
+// The lowercase extension of "path", if any.
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+}
+
+// Standard "source over" alpha blending.
+pub(crate) fn blend(src: Color, dest: Color) -> Color {
+    let alpha = src.a as u32;
+    let inv = 255 - alpha;
+    let mix = |s: u8, d: u8| ((s as u32 * alpha + d as u32 * inv + 127) / 255) as u8;
+    Color::RGBA(
+        mix(src.r, dest.r),
+        mix(src.g, dest.g),
+        mix(src.b, dest.b),
+        (alpha + (dest.a as u32 * inv + 127) / 255) as u8,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> Image {
+        // 2x2: red, green / blue, transparent
+        Image::from_rgba(
+            2,
+            2,
+            vec![
+                255, 0, 0, 255, 0, 255, 0, 255, //
+                0, 0, 255, 255, 0, 0, 0, 0,
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn blit_flip_and_clip() {
+        let mut bytes = vec![0u8; 4 * 4 * 3];
+        let mut fb = FrameBuffer::new(&mut bytes, 12, 4, 4, PixelFormat::RGB24);
+        let options = BlitOptions {
+            flip_x: true,
+            ..Default::default()
+        };
+        // Fully opaque: red, green / blue, white.
+        let mut opaque = checker();
+        opaque.set(1, 1, Color::WHITE);
+        fb.blit_ex(&opaque, 3, -1, &options);
+        // Only the bottom-left pixel of the flipped image is visible, which was bottom-right.
+        assert_eq!(fb.get(3, 0), Some(Color::WHITE));
+        assert_eq!(fb.get(2, 0), Some(Color::BLACK));
+        assert_eq!(fb.get(3, 1), Some(Color::BLACK));
+        fb.blit_ex(&checker(), 0, 0, &options);
+        assert_eq!(fb.get(0, 0), Some(Color::RGB(0, 255, 0)));
+        assert_eq!(fb.get(1, 0), Some(Color::RGB(255, 0, 0)));
+    }
+
+    #[test]
+    fn blit_color_key_and_blend() {
+        let mut bytes = vec![0u8; 2 * 2 * 3];
+        let mut fb = FrameBuffer::new(&mut bytes, 6, 2, 2, PixelFormat::RGB24);
+        fb.fill(Color::WHITE);
+        let options = BlitOptions {
+            color_key: Some(Color::RGB(255, 0, 0)),
+            blend: true,
+            ..Default::default()
+        };
+        fb.blit_ex(&checker(), 0, 0, &options);
+        assert_eq!(fb.get(0, 0), Some(Color::WHITE)); // Color key
+        assert_eq!(fb.get(1, 1), Some(Color::WHITE)); // Transparent
+        assert_eq!(fb.get(0, 1), Some(Color::RGB(0, 0, 255)));
+    }
+
+    #[test]
+    fn blend_half() {
+        let result = blend(Color::RGBA(255, 0, 0, 128), Color::RGBA(0, 0, 255, 255));
+        assert_eq!(result, Color::RGBA(128, 0, 127, 255));
+    }
+
+    #[test]
+    fn qoi_round_trip() {
+        let image = checker();
+        let encoded = qoi::encode_to_vec(image.pixels(), 2, 2).unwrap();
+        assert_eq!(Image::from_qoi(&encoded).unwrap(), image);
+    }
+
+    #[test]
+    fn size_overflow() {
+        assert!(rgba_len(u32::MAX, u32::MAX).is_err());
+        assert!(Image::from_rgba(u32::MAX, u32::MAX, Vec::new()).is_err());
+        // 40000 x 40000 x 4 overflows a u32.
+        assert!(Image::from_rgba(40000, 40000, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = checker();
+        assert_eq!(Image::from_png(&image.to_png().unwrap()).unwrap(), image);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn jpeg_decode() {
+        // A hand made 16x8 grayscale JPEG with two flat blocks, 128 and 136.
+        let mut bytes = vec![0xFF, 0xD8];
+        // Quantization table of ones.
+        bytes.extend_from_slice(&[0xFF, 0xDB, 0x00, 0x43, 0x00]);
+        bytes.extend_from_slice(&[1; 64]);
+        // Baseline, 8 bits, 8 rows of 16 pixels, one component.
+        bytes.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00, 0x08, 0x00, 0x10]);
+        bytes.extend_from_slice(&[0x01, 0x01, 0x11, 0x00]);
+        // DC categories 0 and 7 coded as "00" and "01", and only the end of block for AC.
+        bytes.extend_from_slice(&[0xFF, 0xC4, 0x00, 0x15, 0x00, 0x00, 0x02]);
+        bytes.extend_from_slice(&[0; 14]);
+        bytes.extend_from_slice(&[0x00, 0x07]);
+        bytes.extend_from_slice(&[0xFF, 0xC4, 0x00, 0x14, 0x10, 0x01]);
+        bytes.extend_from_slice(&[0; 15]);
+        bytes.push(0x00);
+        // DC differences of 0 and 64 (8 times 136 - 128), each followed by an end of block.
+        bytes.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+        bytes.extend_from_slice(&[0x0C, 0x07, 0xFF, 0xD9]);
+
+        let image = Image::from_jpeg(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+        assert_eq!(&image.pixels()[..4], &[128, 128, 128, 255]);
+        let last = (16 * 8 - 1) * 4;
+        assert_eq!(&image.pixels()[last..], &[136, 136, 136, 255]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn gif_decode_first_frame() {
+        // A 3x2 GIF with a 2x2 frame offset by one pixel, followed by a second frame.
+        let mut bytes = Vec::new();
+        let palette = [0, 0, 0, 255, 0, 0, 0, 255, 0];
+        let mut encoder = gif::Encoder::new(&mut bytes, 3, 2, &palette).unwrap();
+        let mut frame = gif::Frame {
+            left: 1,
+            width: 2,
+            height: 2,
+            buffer: vec![1, 2, 2, 1].into(),
+            ..Default::default()
+        };
+        encoder.write_frame(&frame).unwrap();
+        frame.buffer = vec![0, 0, 0, 0].into();
+        encoder.write_frame(&frame).unwrap();
+        drop(encoder);
+
+        let image = Image::from_gif(&bytes).unwrap();
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let expected = [[0; 4], red, green, [0; 4], green, red].concat();
+        assert_eq!(image.pixels(), &expected[..]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn gif_zero_sized_frame() {
+        let mut bytes = b"GIF89a".to_vec();
+        // 2x2 with a global palette of 2 colors.
+        bytes.extend_from_slice(&[2, 0, 2, 0, 0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+        // A frame with a width and height of zero, and only the clear and end codes.
+        bytes.extend_from_slice(&[0x2C, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[2, 1, 0x2C, 0, 0x3B]);
+        assert!(Image::from_gif(&bytes).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    create_texture,
+    filter::{self, CachedTarget},
+    Filter, FrameBuffer, PixelFormat, SdlResult, Transform,
+};
+use sdl3::{
+    render::{BlendMode, Canvas, FRect, ScaleMode, Texture},
+    video::Window,
+};
+
+/// How a layer is drawn into.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LayerKind {
+    /// A streaming texture updated pixel by pixel, like the pixel buffer.
+    /// See "Layer::pixel_buffer_update".
+    PixelBuffer,
+    /// A target texture drawn with regular SDL functions, like the render target.
+    /// See "App::layer_canvas".
+    RenderTarget,
+}
+
+/// A named image composited with the others by "App::present_layers", for instance a
+/// background pixel buffer, a sprite render target and a UI render target.
+/// Each layer has its own resolution, and is stretched over the same scaled rect as the
+/// render target, so a layer with twice the App's resolution has pixels half as large.
+/// Layers keep their resolution when the App's resolution changes with "Scaling::Expand".
+pub struct Layer {
+    /// Layers are drawn from the lowest to the highest z. Layers with the same z are
+    /// drawn in the order they were created.
+    pub z: i32,
+    /// Invisible layers are skipped when presenting.
+    pub visible: bool,
+    /// From 0.0 (invisible) to 1.0 (opaque).
+    pub opacity: f32,
+    /// How the layer is combined with the ones below it.
+    pub blend_mode: BlendMode,
+    /// Offset in layer pixels, usually a fraction of the camera position for parallax
+    /// scrolling. Fractional values move the layer by less than one of its pixels.
+    /// Anything moved outside the scaled rect is clipped.
+    pub offset: (f32, f32),
+    /// The texture filter used when compositing this layer.
+    pub filter: Filter,
+    /// The layer's pixels. Prefer "pixel_buffer_update" or "App::layer_canvas".
+    pub texture: Texture,
+    name: String,
+    kind: LayerKind,
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+    prescaler: CachedTarget,
+}
+
+impl Layer {
+    pub(crate) fn new(
+        canvas: &mut Canvas<Window>,
+        name: &str,
+        kind: LayerKind,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> SdlResult<Self> {
+        let mut texture = create_texture(canvas, kind, format, width, height)?;
+        texture.set_blend_mode(BlendMode::Blend);
+        Ok(Self {
+            z: 0,
+            visible: true,
+            opacity: 1.0,
+            blend_mode: BlendMode::Blend,
+            offset: (0.0, 0.0),
+            filter: Filter::Nearest,
+            texture,
+            name: name.to_string(),
+            kind,
+            format,
+            width,
+            height,
+            prescaler: CachedTarget::default(),
+        })
+    }
+
+    /// The name used to find this layer.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How the layer is drawn into.
+    pub fn kind(&self) -> LayerKind {
+        self.kind
+    }
+
+    /// The format of the layer's pixels.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Locks a pixel buffer layer and provides its raw bytes and pitch, like
+    /// "App::pixel_buffer_update".
+    pub fn pixel_buffer_update<F, R>(&mut self, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut [u8], usize) -> R,
+    {
+        self.check_pixel_buffer()?;
+        self.texture
+            .with_lock(None, |pixels: &mut [u8], pitch: usize| {
+                func(pixels, pitch);
+            })?;
+        Ok(())
+    }
+
+    /// Locks a pixel buffer layer and provides it as a FrameBuffer, like
+    /// "App::pixel_buffer_frame".
+    pub fn pixel_buffer_frame<F, R>(&mut self, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut FrameBuffer) -> R,
+    {
+        self.check_pixel_buffer()?;
+        let (width, height, format) = (self.width, self.height, self.format);
+        self.texture
+            .with_lock(None, |pixels: &mut [u8], pitch: usize| {
+                func(&mut FrameBuffer::new(pixels, pitch, width, height, format));
+            })?;
+        Ok(())
+    }
+
+    fn check_pixel_buffer(&self) -> SdlResult<()> {
+        if self.kind != LayerKind::PixelBuffer {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Layer is not a pixel buffer, use App::layer_canvas instead",
+            )));
+        }
+        Ok(())
+    }
+
+    // Scales the layer to the size of "dest" when using "Filter::SharpBilinear". Must be called
+    // before "composite", which can't render to the intermediate texture while compositing
+    // into another render target.
+    pub(crate) fn prepare(&mut self, canvas: &mut Canvas<Window>, dest: FRect) -> SdlResult<()> {
+        if self.filter == Filter::SharpBilinear {
+            filter::prescale(
+                canvas,
+                &mut self.texture,
+                (self.width, self.height),
+                (dest.w.round() as u32, dest.h.round() as u32),
+                &mut self.prescaler,
+            )?;
+        }
+        Ok(())
+    }
+
+    // Draws the layer stretched over "dest", with its offset, opacity and blend mode.
+    // The offset moves the layer within "dest", and the part that falls outside is cropped.
+    // "dest" includes the offset and zoom of "transform", which adds rotation and flips.
+    pub(crate) fn composite(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        dest: FRect,
+        transform: &Transform,
+    ) -> SdlResult<()> {
+        let (dest_x, dest_y, dest_w, dest_h) = (dest.x, dest.y, dest.w, dest.h);
+        let shift_x = self.offset.0 * dest_w / self.width as f32;
+        let shift_y = self.offset.1 * dest_h / self.height as f32;
+        // The visible part, as fractions of the layer.
+        let (left, right) = (
+            (-shift_x / dest_w).max(0.0),
+            (1.0 - shift_x / dest_w).min(1.0),
+        );
+        let (top, bottom) = (
+            (-shift_y / dest_h).max(0.0),
+            (1.0 - shift_y / dest_h).min(1.0),
+        );
+        if right <= left || bottom <= top {
+            return Ok(());
+        }
+
+        let texture = match self.filter {
+            Filter::SharpBilinear => self.prescaler.texture().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Layer was not prepared")
+            })?,
+            Filter::Nearest => {
+                self.texture.set_scale_mode(ScaleMode::Nearest);
+                &mut self.texture
+            }
+            Filter::Linear => {
+                self.texture.set_scale_mode(ScaleMode::Linear);
+                &mut self.texture
+            }
+        };
+        texture.set_blend_mode(self.blend_mode);
+        texture.set_alpha_mod((self.opacity.clamp(0.0, 1.0) * 255.0).round() as u8);
+
+        let (texture_w, texture_h) = (texture.width() as f32, texture.height() as f32);
+        let source = FRect::new(
+            left * texture_w,
+            top * texture_h,
+            (right - left) * texture_w,
+            (bottom - top) * texture_h,
+        );
+        let target = FRect::new(
+            dest_x + shift_x + left * dest_w,
+            dest_y + shift_y + top * dest_h,
+            (right - left) * dest_w,
+            (bottom - top) * dest_h,
+        );
+        transform.copy(canvas, texture, Some(source), target, dest)
+    }
+
+    // Required with "unsafe_textures".
+    pub(crate) fn destroy(self) {
+        unsafe { self.texture.destroy() };
+        self.prescaler.destroy();
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{filter::CachedTarget, SdlResult, Transform};
+use sdl3::{
+    rect::Rect,
+    render::{Canvas, FRect, Texture},
+    video::Window,
+};
+
+/// What fills the area around the render target when it doesn't cover the whole window.
+/// Drawn by "pixel_buffer_present" and "render_target_present" before the main image, so it will
+/// cover anything drawn to the canvas before presenting.
+#[derive(Default)]
+pub enum Letterbox {
+    #[default]
+    /// Leaves the area untouched, showing the background color.
+    None,
+
+    /// Fills the area with a solid color.
+    Color((u8, u8, u8, u8)),
+
+    /// A border image centered on the render target and scaled by the same factor, like the
+    /// Super Game Boy borders. The image should be created from the App's canvas and is usually
+    /// larger than the render target, with a transparent (or covered) hole in the middle.
+    Image(Texture),
+
+    /// A blurred, darkened copy of the current frame stretched to cover the window.
+    /// "brightness" ranges from 0 (black) to 255 (unchanged).
+    Blur { brightness: u8 },
+}
+
+// Draws the letterbox. "frame" is the texture about to be presented at "dest", which
+// includes the offset and zoom of "transform".
+pub(crate) fn draw_letterbox(
+    canvas: &mut Canvas<Window>,
+    letterbox: &mut Letterbox,
+    frame: &Texture,
+    frame_size: (u32, u32),
+    dest: FRect,
+    transform: &Transform,
+    blur_target: &mut CachedTarget,
+) -> SdlResult<()> {
+    let (output_width, output_height) = canvas.output_size()?;
+    match letterbox {
+        Letterbox::None => {}
+        Letterbox::Color(color) => {
+            let (ow, oh) = (output_width as f32, output_height as f32);
+            // Clamped, since the transform can move "dest" partially out of the window.
+            let (left, top) = (dest.x.clamp(0.0, ow), dest.y.clamp(0.0, oh));
+            let right = (dest.x + dest.w).clamp(left, ow);
+            let bottom = (dest.y + dest.h).clamp(top, oh);
+            canvas.set_draw_color(*color);
+            // Top, bottom, left and right bars
+            canvas.fill_rect(FRect::new(0.0, 0.0, ow, top))?;
+            canvas.fill_rect(FRect::new(0.0, bottom, ow, oh - bottom))?;
+            canvas.fill_rect(FRect::new(0.0, top, left, bottom - top))?;
+            canvas.fill_rect(FRect::new(right, top, ow - right, bottom - top))?;
+            canvas.set_draw_color((255, 255, 255, 255));
+        }
+        Letterbox::Image(image) => {
+            let scale = dest.w / frame_size.0 as f32;
+            let width = image.width() as f32 * scale;
+            let height = image.height() as f32 * scale;
+            let rect = FRect::new(
+                dest.x + (dest.w - width) / 2.0,
+                dest.y + (dest.h - height) / 2.0,
+                width,
+                height,
+            );
+            // Same center as "dest", so it rotates and flips along with the frame.
+            transform.copy(canvas, image, None, rect, rect)?;
+        }
+        Letterbox::Blur { brightness } => {
+            // Downscaling to a tiny texture, then scaling it back up with linear
+            // filtering is a cheap blur.
+            let small = blur_target.get(
+                canvas,
+                (frame_size.0 / 16).max(1),
+                (frame_size.1 / 16).max(1),
+            )?;
+            let mut result = Ok(());
+            canvas.with_texture_canvas(small, |target| {
+                result = target.copy(frame, None, None);
+            })?;
+            result?;
+            small.set_color_mod(*brightness, *brightness, *brightness);
+            // Covers the whole output while preserving the aspect ratio.
+            let scale = (output_width as f32 / frame_size.0 as f32)
+                .max(output_height as f32 / frame_size.1 as f32);
+            let width = frame_size.0 as f32 * scale;
+            let height = frame_size.1 as f32 * scale;
+            let rect = Rect::new(
+                ((output_width as f32 - width) / 2.0) as i32,
+                ((output_height as f32 - height) / 2.0) as i32,
+                width as u32,
+                height as u32,
+            );
+            canvas.copy(small, None, rect)?;
+        }
+    }
+    Ok(())
+}
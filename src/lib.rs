@@ -1,22 +1,60 @@
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/readme.md"))]
 
+mod atlas_packer;
+mod capture;
+mod draw;
+mod filter;
+mod frame_buffer;
+mod image;
+mod layer;
+mod letterbox;
+mod palette;
+mod pixel_format;
+mod post_process;
+mod recorder;
 mod scaling;
+mod sprite_batch;
+mod tiled;
+mod tilemap;
 mod timing;
-
+mod tool_window;
+mod transform;
+mod transition;
+mod viewport;
+mod window_mode;
+
+use capture::Capture;
+use filter::CachedTarget;
+use recorder::Recorder;
 use sdl3::audio::{AudioFormat, AudioSpec, AudioStreamOwner};
 use sdl3::gamepad::Gamepad;
-
-use sdl3::pixels::PixelFormat;
-use sdl3::sys::pixels::SDL_PixelFormat;
+use transition::TransitionState;
 
 pub use smooth_buffer::SmoothBuffer;
 pub use smooth_buffer::{Float, Num};
 
+pub use atlas_packer::{AtlasPacker, AtlasRect};
+pub use capture::{CaptureFormat, CaptureOptions, CaptureSource};
+pub use filter::{Filter, ScalingFilters};
+pub use frame_buffer::FrameBuffer;
+pub use image::{BlitOptions, Image};
+pub use layer::{Layer, LayerKind};
+pub use letterbox::Letterbox;
 pub use padstate::*;
+pub use palette::Palette;
+pub use pixel_format::PixelFormat;
+pub use post_process::{Effect, PostProcess};
 pub use scaling::Scaling;
 
 pub use sdl3;
+pub use sprite_batch::{Sprite, SpriteBatch};
+pub use tilemap::{Camera, Tile, TileLayer, Tilemap, Tileset};
 pub use timing::Timing;
+pub use tool_window::ToolWindow;
+pub use transform::{ScreenShake, Transform};
+pub use transition::Transition;
+pub use viewport::Viewport;
+pub use window_mode::WindowMode;
 
 #[cfg(feature = "ttf")]
 mod font_atlas;
@@ -24,11 +62,13 @@ mod font_atlas;
 pub use font_atlas::FontAtlas;
 
 use sdl3::{
-    event::Event,
+    event::{Event, WindowEvent},
     keyboard::{Keycode, Mod},
+    mouse::Cursor,
     rect::Rect,
-    render::{Canvas, Texture},
-    video::Window,
+    render::{BlendMode, Canvas, FRect, ScaleMode, Texture},
+    surface::Surface,
+    video::{Display, DisplayMode, Window, WindowPos},
     EventPump, Sdl,
 };
 use std::time::{Duration, Instant};
@@ -54,6 +94,15 @@ pub struct App {
     pub bg_color: (u8, u8, u8, u8),
     /// Controls whether text overlay is visible.
     pub display_overlay: bool,
+    /// The texture filter used when presenting the pixel buffer or render target, for each
+    /// scaling strategy.
+    pub filters: ScalingFilters,
+    /// Fills the area around the render target, if any.
+    pub letterbox: Letterbox,
+    /// Moves, zooms, rotates and flips the presented image.
+    pub transform: Transform,
+    /// Shake added to "transform", see "ScreenShake::add_trauma".
+    pub screen_shake: ScreenShake,
     // SDL
     /// The internal SDL canvas. It is automatically cleared on every frame start.
     pub canvas: Canvas<Window>,
@@ -74,6 +123,11 @@ pub struct App {
     pub render_target: Option<Texture>,
     /// A pixel buffer that you can manipule using "pixel_buffer_update()"
     pub pixel_buffer: Option<Texture>,
+    /// Optional CPU effects applied to the pixel buffer, see "post_process_init".
+    pub post_process: Option<PostProcess>,
+    /// The palette used to convert the indexed pixel buffer to RGB on present.
+    /// Only used if the pixel buffer was initialized with "init_pixel_buffer_indexed".
+    pub palette: Palette,
     /// The SDL TTF context
     #[cfg(feature = "ttf")]
     pub fonts: sdl3::ttf::Sdl3TtfContext,
@@ -92,15 +146,38 @@ pub struct App {
     pub overlay_coords: sdl3::rect::Point,
     #[cfg(feature = "ttf")]
     overlay: Vec<String>,
+    // Additional windows
+    tool_windows: Vec<ToolWindow>,
+    // Named layers, see "layer_create"
+    layers: Vec<Layer>,
+    // Transitions
+    transition: Option<TransitionState>,
+    // A copy of the last presented frame, where the next transition starts from.
+    last_frame: CachedTarget,
+    // Recording, see "capture_start" and "record_start"
+    capture: Option<Capture>,
+    recorder: Option<Recorder>,
+    // Cursor
+    cursor: Option<Cursor>,
     // Audio
     pub audio_stream: Option<AudioStreamOwner>,
     sample_rate: Option<u32>,
     // Video
+    indices: Option<Vec<u8>>,
+    pixel_buffer_format: PixelFormat,
+    render_target_format: PixelFormat,
     width: u32,
     height: u32,
     dpi_mult: f32,
+    window_resized: bool,
+    resolution_changed: bool,
     timing: Timing,
     scaling: Scaling,
+    prescaler: CachedTarget,
+    // The visible layers composited by "present_layers".
+    layers_frame: CachedTarget,
+    letterbox_blur: CachedTarget,
+    window_mode: WindowMode,
     // Timing,
     app_time: Instant,
     last_second: Instant,
@@ -164,7 +241,7 @@ impl App {
         // sdl3::hint::set("SDL_RENDER_VSYNC", "1");
         let video_subsystem = context.video()?;
 
-        let window = video_subsystem
+        let mut window = video_subsystem
             .window(name, width * 2, height * 2)
             .high_pixel_density()
             .position_centered()
@@ -172,6 +249,15 @@ impl App {
             .opengl() // lowers CPU use, but may not work in the future?
             .build()?;
 
+        // Prevents shrinking the window below 1x the render target.
+        match scaling {
+            Scaling::Expand {
+                min_width,
+                min_height,
+            } => window.set_minimum_size(min_width, min_height)?,
+            _ => window.set_minimum_size(width, height)?,
+        }
+
         let canvas = window.into_canvas();
 
         let dpi_mult = canvas.window().pixel_density();
@@ -183,6 +269,10 @@ impl App {
             print_fps_interval: None,
             bg_color: (0, 0, 0, 255),
             display_overlay: true,
+            filters: ScalingFilters::default(),
+            letterbox: Letterbox::None,
+            transform: Transform::default(),
+            screen_shake: ScreenShake::default(),
             app_time: Instant::now(),
             last_second: Instant::now(),
             frame_start: Instant::now(),
@@ -192,10 +282,21 @@ impl App {
             width,
             height,
             dpi_mult,
+            window_resized: false,
+            resolution_changed: false,
             timing,
             scaling,
+            prescaler: CachedTarget::default(),
+            layers_frame: CachedTarget::default(),
+            letterbox_blur: CachedTarget::default(),
+            window_mode: WindowMode::Windowed,
             canvas,
             pixel_buffer: None,
+            post_process: None,
+            palette: Palette::default(),
+            indices: None,
+            pixel_buffer_format: PixelFormat::RGB24,
+            render_target_format: PixelFormat::RGB24,
             render_target: None,
             context,
             events,
@@ -203,6 +304,13 @@ impl App {
             allow_analog_to_dpad_x: false,
             allow_analog_to_dpad_y: false,
             // texture_creator,
+            tool_windows: Vec::new(),
+            layers: Vec::new(),
+            transition: None,
+            last_frame: CachedTarget::default(),
+            capture: None,
+            recorder: None,
+            cursor: None,
             // Audio
             sample_rate: None,
             audio_stream: None,
@@ -232,57 +340,112 @@ impl App {
         self.height
     }
 
-    /// Initializes the Pixel Buffer with the current width and height settings.
+    /// Initializes the Pixel Buffer with the current width and height settings, in RGB24 format.
     pub fn init_pixel_buffer(&mut self) -> SdlResult<()> {
-        // let pixel_buffer = self.canvas.texture_creator().create_texture_streaming(
-        //     unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) },
-        //     self.width,
-        //     self.height,
-        // )?;
-        let pixel_buffer = self.canvas.create_texture_streaming(
-            unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) },
+        self.init_pixel_buffer_format(PixelFormat::RGB24)
+    }
+
+    /// Initializes the Pixel Buffer with the current width and height settings, using any
+    /// of the available pixel formats. Post-processing requires RGB24.
+    pub fn init_pixel_buffer_format(&mut self, format: PixelFormat) -> SdlResult<()> {
+        replace_texture(
+            &mut self.canvas,
+            &mut self.pixel_buffer,
+            LayerKind::PixelBuffer,
+            format,
             self.width,
             self.height,
         )?;
-        self.pixel_buffer = Some(pixel_buffer);
+        self.pixel_buffer_format = format;
+        self.indices = None;
         Ok(())
     }
 
-    /// Initializes the Render Target with the current width and height settings.
+    /// The format of the pixel buffer.
+    pub fn pixel_buffer_format(&self) -> PixelFormat {
+        self.pixel_buffer_format
+    }
+
+    /// How many bytes each pixel of the pixel buffer takes.
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.pixel_buffer_format.bytes_per_pixel()
+    }
+
+    /// Initializes the Pixel Buffer in indexed mode: "pixel_buffer_update" will provide one byte
+    /// per pixel, an index into "palette", and the conversion to RGB happens on present.
+    pub fn init_pixel_buffer_indexed(&mut self) -> SdlResult<()> {
+        self.init_pixel_buffer()?;
+        self.indices = Some(vec![0; (self.width * self.height) as usize]);
+        Ok(())
+    }
+
+    /// True if the pixel buffer was initialized in indexed mode.
+    pub fn pixel_buffer_is_indexed(&self) -> bool {
+        self.indices.is_some()
+    }
+
+    /// Initializes the Render Target with the current width and height settings, in RGB24 format.
     pub fn init_render_target(&mut self) -> SdlResult<()> {
-        // let render_target = self.canvas.texture_creator().create_texture_target(
-        //     unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) },
-        //     self.width,
-        //     self.height,
-        // )?;
-        let render_target = self.canvas.create_texture_target(
-            unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) },
+        self.init_render_target_format(PixelFormat::RGB24)
+    }
+
+    /// Initializes the Render Target with the current width and height settings, using any of the
+    /// available pixel formats. Use a format with alpha if the render target needs transparency.
+    pub fn init_render_target_format(&mut self, format: PixelFormat) -> SdlResult<()> {
+        replace_texture(
+            &mut self.canvas,
+            &mut self.render_target,
+            LayerKind::RenderTarget,
+            format,
             self.width,
             self.height,
         )?;
-        self.render_target = Some(render_target);
+        self.render_target_format = format;
         Ok(())
     }
 
-    // /// Resizes the render target. Causes a memory leak with SDL,
-    // /// at least with "unsafe_textures", so I'm leaving it disabled
-    // pub fn set_size(&mut self, w: u32, h: u32) -> SdlResult<()> {
-    //     if w == self.width && h == self.height {
-    //         return Ok(())
-    //     }
-    //     self.width = w;
-    //     self.height = h;
+    /// The format of the render target.
+    pub fn render_target_format(&self) -> PixelFormat {
+        self.render_target_format
+    }
+
+    /// Enables the post-processing chain, which renders the pixel buffer at "scale" times its
+    /// resolution with the effects in "post_process.effects". Higher scales allow finer
+    /// effects, at the cost of CPU time.
+    pub fn post_process_init(&mut self, scale: u32) {
+        self.post_process = Some(PostProcess::new(scale));
+    }
+
+    /// True if the render target resolution changed since the last frame start, which only
+    /// happens when using "Scaling::Expand".
+    pub fn resolution_changed(&self) -> bool {
+        self.resolution_changed
+    }
 
-    //     if self.pixel_buffer.is_some() {
-    //         self.init_pixel_buffer()?;
-    //     }
+    // Resizes the pixel buffer and render target, if they're initialized.
+    fn set_size(&mut self, w: u32, h: u32) -> SdlResult<()> {
+        if w == self.width && h == self.height {
+            return Ok(());
+        }
+        self.width = w;
+        self.height = h;
+        self.resolution_changed = true;
+
+        // Re-initializing the pixel buffer clears the indices, so indexed mode is restored here.
+        if self.pixel_buffer.is_some() {
+            if self.indices.is_some() {
+                self.init_pixel_buffer_indexed()?;
+            } else {
+                self.init_pixel_buffer_format(self.pixel_buffer_format)?;
+            }
+        }
 
-    //     if self.render_target.is_some() {
-    //         self.init_render_target()?;
-    //     }
+        if self.render_target.is_some() {
+            self.init_render_target_format(self.render_target_format)?;
+        }
 
-    //     Ok(())
-    // }
+        Ok(())
+    }
 
     /// The window width, which is independent from the render target.
     pub fn window_width(&self) -> u32 {
@@ -294,6 +457,248 @@ impl App {
         self.canvas.window().size().1
     }
 
+    /// The current window mode.
+    pub fn window_mode(&self) -> WindowMode {
+        self.window_mode
+    }
+
+    /// Switches between windowed, borderless fullscreen and exclusive fullscreen modes.
+    /// The fullscreen modes use the display the window is currently in, see "set_display".
+    pub fn set_window_mode(&mut self, mode: WindowMode) -> SdlResult<()> {
+        let window = self.canvas.window_mut();
+        match mode {
+            WindowMode::Windowed => {
+                window.set_fullscreen(false)?;
+            }
+            WindowMode::BorderlessFullscreen => {
+                window.set_display_mode(None)?;
+                window.set_fullscreen(true)?;
+            }
+            WindowMode::ExclusiveFullscreen(display_mode) => {
+                window.set_display_mode(Some(display_mode))?;
+                window.set_fullscreen(true)?;
+            }
+        }
+        self.window_mode = mode;
+        self.update_pixel_density();
+        Ok(())
+    }
+
+    /// Toggles between windowed and borderless fullscreen modes. If the window is in exclusive
+    /// fullscreen mode it will return to windowed mode.
+    pub fn toggle_fullscreen(&mut self) -> SdlResult<()> {
+        match self.window_mode {
+            WindowMode::Windowed => self.set_window_mode(WindowMode::BorderlessFullscreen),
+            _ => self.set_window_mode(WindowMode::Windowed),
+        }
+    }
+
+    /// All the displays currently connected.
+    pub fn displays(&self) -> SdlResult<Vec<Display>> {
+        Ok(self.context.video()?.displays()?)
+    }
+
+    /// The display the window is currently in.
+    pub fn current_display(&self) -> SdlResult<Display> {
+        Ok(self.canvas.window().get_display()?)
+    }
+
+    /// The fullscreen modes supported by the display the window is currently in. Any of them can
+    /// be used with "WindowMode::ExclusiveFullscreen".
+    pub fn display_modes(&self) -> SdlResult<Vec<DisplayMode>> {
+        Ok(self.current_display()?.get_fullscreen_modes()?)
+    }
+
+    /// Moves the window to the center of the display at "index" (see "displays").
+    /// If the window is in a fullscreen mode it will be re-applied on the new display.
+    pub fn set_display(&mut self, index: usize) -> SdlResult<()> {
+        let displays = self.displays()?;
+        let Some(display) = displays.get(index) else {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Display not found",
+            )));
+        };
+        let bounds = display.get_bounds()?;
+        let mode = self.window_mode;
+        if mode != WindowMode::Windowed {
+            self.set_window_mode(WindowMode::Windowed)?;
+        }
+        let (window_width, window_height) = self.canvas.window().size();
+        let x = bounds.x() + (bounds.width() as i32 - window_width as i32) / 2;
+        let y = bounds.y() + (bounds.height() as i32 - window_height as i32) / 2;
+        self.canvas
+            .window_mut()
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+        if let WindowMode::ExclusiveFullscreen(display_mode) = mode {
+            // Display modes belong to a specific display, pick the closest one in the new display.
+            let new_mode = display
+                .get_fullscreen_modes()?
+                .into_iter()
+                .find(|m| m.w == display_mode.w && m.h == display_mode.h)
+                .unwrap_or(display.get_mode()?);
+            self.set_window_mode(WindowMode::ExclusiveFullscreen(new_mode))?;
+        } else {
+            self.set_window_mode(mode)?;
+        }
+        Ok(())
+    }
+
+    /// Resizes the window to an integer multiple of the render target size. Returns to windowed
+    /// mode if necessary.
+    pub fn set_window_scale(&mut self, scale: u32) -> SdlResult<()> {
+        if self.window_mode != WindowMode::Windowed {
+            self.set_window_mode(WindowMode::Windowed)?;
+        }
+        let scale = scale.max(1);
+        self.canvas
+            .window_mut()
+            .set_size(self.width * scale, self.height * scale)?;
+        self.update_pixel_density();
+        Ok(())
+    }
+
+    /// Resizes the window to the largest integer multiple of the render target that fits the usable
+    /// bounds of the current display (which excludes things like task bars and docks), then
+    /// centers it. Returns the scale used.
+    pub fn fit_window_to_display(&mut self) -> SdlResult<u32> {
+        let bounds = self.current_display()?.get_usable_bounds()?;
+        let scale_x = bounds.width() / self.width;
+        let scale_y = bounds.height() / self.height;
+        let scale = scale_x.min(scale_y).max(1);
+        self.set_window_scale(scale)?;
+        self.canvas
+            .window_mut()
+            .set_position(WindowPos::Centered, WindowPos::Centered);
+        Ok(scale)
+    }
+
+    /// The ratio between window pixels and window coordinates, i.e. 2.0 on most Retina displays.
+    /// Updated automatically when the window moves to a different display.
+    pub fn pixel_density(&self) -> f32 {
+        self.dpi_mult
+    }
+
+    /// True if the window size or pixel density changed since the last frame start.
+    pub fn window_resized(&self) -> bool {
+        self.window_resized
+    }
+
+    /// Changes the window title.
+    pub fn set_title(&mut self, title: &str) -> SdlResult<()> {
+        self.canvas.window_mut().set_title(title)?;
+        Ok(())
+    }
+
+    /// Sets the window icon from RGBA pixels, 4 bytes per pixel.
+    pub fn set_icon_from_rgba(&mut self, rgba: &[u8], width: u32, height: u32) -> SdlResult<()> {
+        let mut pixels = scale_rgba(rgba, width, height, 1)?;
+        let surface = Surface::from_data(
+            &mut pixels,
+            width,
+            height,
+            width * 4,
+            PixelFormat::RGBA32.to_sdl(),
+        )?;
+        self.canvas.window_mut().set_icon(surface);
+        Ok(())
+    }
+
+    /// Shows or hides the mouse cursor while it's over the window.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.context.mouse().show_cursor(visible);
+    }
+
+    /// Replaces the mouse cursor with RGBA pixels, 4 bytes per pixel. The image is scaled by the
+    /// current integer scale factor of the render target, so pixel art cursors match the game.
+    /// "hot_x" and "hot_y" are the cursor's click point, in unscaled pixels.
+    /// Call it again after the window is resized if you want the cursor to follow the scale.
+    pub fn set_cursor_from_rgba(
+        &mut self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        hot_x: i32,
+        hot_y: i32,
+    ) -> SdlResult<()> {
+        // In pixels, not window coordinates, so the cursor matches the game on HiDPI displays.
+        let (window_width, window_height) = self.canvas.window().size();
+        let window_width = (window_width as f32 * self.dpi_mult) as u32;
+        let window_height = (window_height as f32 * self.dpi_mult) as u32;
+        let scale = (window_width / self.width)
+            .min(window_height / self.height)
+            .max(1);
+        let mut pixels = scale_rgba(rgba, width, height, scale)?;
+        let surface = Surface::from_data(
+            &mut pixels,
+            width * scale,
+            height * scale,
+            width * scale * 4,
+            PixelFormat::RGBA32.to_sdl(),
+        )?;
+        let cursor = Cursor::from_surface(surface, hot_x * scale as i32, hot_y * scale as i32)?;
+        cursor.set();
+        // The cursor needs to be kept alive while in use.
+        self.cursor = Some(cursor);
+        Ok(())
+    }
+
+    /// Restores the system's default cursor.
+    pub fn reset_cursor(&mut self) {
+        // Dropping the custom cursor restores the default one.
+        self.cursor = None;
+    }
+
+    /// In relative mouse mode the cursor is hidden and the mouse reports only relative motion,
+    /// which is unaffected by the window borders. Useful for mouse-look and similar controls.
+    pub fn set_relative_mouse(&mut self, enabled: bool) {
+        self.context
+            .mouse()
+            .set_relative_mouse_mode(self.canvas.window(), enabled);
+    }
+
+    /// Confines the mouse cursor to the window.
+    pub fn set_mouse_grab(&mut self, grabbed: bool) {
+        self.canvas.window_mut().set_mouse_grab(grabbed);
+    }
+
+    /// Creates an additional window with its own canvas and scaling strategy, and returns its id.
+    /// Tool windows are cleared and presented along with the main window, and closing them does
+    /// not quit the App (see "ToolWindow::close_requested").
+    pub fn window_create(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        scaling: Scaling,
+    ) -> SdlResult<u32> {
+        let video_subsystem = self.context.video()?;
+        let tool_window = ToolWindow::new(&video_subsystem, name, width, height, scaling)?;
+        let id = tool_window.id();
+        self.tool_windows.push(tool_window);
+        Ok(id)
+    }
+
+    /// Provides access to a tool window created with "window_create".
+    pub fn window(&mut self, id: u32) -> Option<&mut ToolWindow> {
+        self.tool_windows.iter_mut().find(|w| w.id() == id)
+    }
+
+    /// Iterates over all tool windows.
+    pub fn windows(&mut self) -> impl Iterator<Item = &mut ToolWindow> {
+        self.tool_windows.iter_mut()
+    }
+
+    /// Destroys a tool window. Does nothing if the id is not found.
+    pub fn window_close(&mut self, id: u32) {
+        self.tool_windows.retain(|w| w.id() != id);
+    }
+
+    // Reads the pixel density again, since it can change with the window mode or display.
+    fn update_pixel_density(&mut self) {
+        self.dpi_mult = self.canvas.window().pixel_density();
+    }
+
     /// The amount of time in seconds each frame takes to update and draw.
     /// Necessary to correctly implement delta timing, if you wish to do so.
     /// Performs quantization, rounding it to the nearest most like display frequency
@@ -345,6 +750,16 @@ impl App {
         )
     }
 
+    /// Loads a PNG, QOI or BMP file into a static texture with alpha blending enabled. JPEG and
+    /// GIF (first frame) files are also supported with the "image" feature. Use "Image::load"
+    /// to keep the pixels in CPU memory instead.
+    pub fn texture_load<P>(&mut self, path: P) -> SdlResult<Texture>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Image::load(path)?.to_texture(&self.canvas.texture_creator())
+    }
+
     /// Required at the start of a frame loop, performs basic timing math, clears the canvas and
     /// updates self.pad with the current values.
     pub fn frame_start(&mut self) -> SdlResult<()> {
@@ -359,12 +774,40 @@ impl App {
             Timing::Immediate | Timing::ImmediateLimitFPS(_) => self.elapsed_time_raw,
         };
 
+        self.screen_shake.update(self.elapsed_time);
+
+        // Transition
+        if let Some(transition) = &mut self.transition {
+            if !transition.update(self.elapsed_time) {
+                if let Some(transition) = self.transition.take() {
+                    transition.destroy();
+                }
+            }
+        }
+
         // Input
         self.pad.copy_current_to_previous_state();
+        self.window_resized = false;
+        self.resolution_changed = false;
+        for tool_window in &mut self.tool_windows {
+            tool_window.frame_start();
+        }
+        let main_window_id = self.canvas.window().id();
 
         for event in self.events.poll_iter() {
             use padstate::Button as butt;
             use sdl3::gamepad::Button::*;
+            // Input sent to a tool window (while it has focus) doesn't reach the main window.
+            if let Some(window_id) = event.get_window_id() {
+                if window_id != main_window_id {
+                    if let Some(tool_window) =
+                        self.tool_windows.iter_mut().find(|w| w.id() == window_id)
+                    {
+                        tool_window.handle_event(event);
+                        continue;
+                    }
+                }
+            }
             match event {
                 Event::ControllerAxisMotion { axis, value, .. } => {
                     use sdl3::gamepad::Axis::*;
@@ -494,66 +937,238 @@ impl App {
                         Some(_) => {} // ignore the rest
                     }
                 }
+                Event::Window { win_event, .. } => match win_event {
+                    WindowEvent::CloseRequested => self.quit_requested = true,
+                    WindowEvent::Resized(..) | WindowEvent::PixelSizeChanged(..) => {
+                        self.window_resized = true;
+                        self.dpi_mult = self.canvas.window().pixel_density();
+                    }
+                    WindowEvent::DisplayChanged(..) => {
+                        // Moving to a display with a different density doesn't always
+                        // trigger a pixel size change.
+                        self.window_resized = true;
+                        self.dpi_mult = self.canvas.window().pixel_density();
+                    }
+                    _ => {}
+                },
                 Event::Quit { .. } => self.quit_requested = true,
                 _ => {}
             }
         }
+        // Adapts the resolution to the window
+        if let Scaling::Expand {
+            min_width,
+            min_height,
+        } = self.scaling
+        {
+            let (w, h) = scaling::expanded_size(min_width, min_height, self.canvas.window().size());
+            self.set_size(w, h)?;
+        }
+        for tool_window in &mut self.tool_windows {
+            tool_window.update_resolution()?;
+        }
+
         self.canvas.set_draw_color(self.bg_color);
         self.canvas.clear();
         self.canvas.set_draw_color((255, 255, 255, 255));
         Ok(())
     }
 
-    /// Uses SDL's "texture.with_lock" function to access the pixel buffer as an RGB array.
+    /// Uses SDL's "texture.with_lock" function to access the pixel buffer as an array of bytes in
+    /// the pixel buffer format (RGB24 by default). If post-processing is enabled, a CPU-side RGB
+    /// buffer is provided instead. In indexed mode each pixel is a single byte, and the pitch
+    /// equals the width.
     pub fn pixel_buffer_update<F, R>(&mut self, func: F) -> SdlResult<()>
     where
         F: FnOnce(&mut [u8], usize) -> R,
     {
-        let Some(buffer) = &mut self.pixel_buffer else {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Pixel buffer not initialized",
-            )));
-        };
+        if let Some(indices) = &mut self.indices {
+            func(indices, self.width as usize);
+            return Ok(());
+        }
+        if let Some(post) = &mut self.post_process {
+            if post.uses_source(self.pixel_buffer_format) {
+                post.resize(self.width, self.height);
+                let (buffer, pitch) = post.source_mut();
+                func(buffer, pitch);
+                return Ok(());
+            }
+        }
+        let buffer = initialized(&mut self.pixel_buffer, "Pixel buffer")?;
         buffer.with_lock(None, func)?;
         Ok(())
     }
 
-    fn get_scaled_rect(&self) -> Option<Rect> {
-        match self.scaling {
-            Scaling::Integer | Scaling::PreserveAspect => {
-                let window_size = self.canvas.window().size();
-                let scale = match self.scaling {
-                    Scaling::Integer => (window_size.1 as f32 / self.height as f32).floor(),
-                    Scaling::PreserveAspect => window_size.1 as f32 / self.height as f32,
-                    _ => 1.0,
-                };
-                let new_width = self.width as f32 * scale;
-                let new_height = self.height as f32 * scale;
-                let gap_x = ((window_size.0 as f32 - new_width) * self.dpi_mult) / 2.0;
-                let gap_y = ((window_size.1 as f32 - new_height) * self.dpi_mult) / 2.0;
-                Some(Rect::new(
-                    gap_x as i32,
-                    gap_y as i32,
-                    (new_width * self.dpi_mult) as u32,
-                    (new_height * self.dpi_mult) as u32,
-                ))
+    /// Same as "pixel_buffer_update", but provides one u32 per pixel for the 32 bit formats
+    /// (RGBA32, ARGB8888, XRGB8888). The second argument is the pitch in pixels, not bytes.
+    pub fn pixel_buffer_update_u32<F, R>(&mut self, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut [u32], usize) -> R,
+    {
+        self.check_32_bit_format()?;
+        let mut result = Ok(());
+        self.pixel_buffer_update(|bytes: &mut [u8], pitch: usize| {
+            let (prefix, pixels, suffix) = unsafe { bytes.align_to_mut::<u32>() };
+            if !prefix.is_empty() || !suffix.is_empty() {
+                result = Err(misaligned_pixels());
+                return;
+            }
+            func(pixels, pitch / 4);
+        })?;
+        result
+    }
+
+    /// Same as "pixel_buffer_update", but provides 4 bytes per pixel for the 32 bit formats
+    /// (RGBA32, ARGB8888, XRGB8888), in memory order. The second argument is the pitch in pixels,
+    /// not bytes.
+    pub fn pixel_buffer_update_rgba<F, R>(&mut self, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut [[u8; 4]], usize) -> R,
+    {
+        self.check_32_bit_format()?;
+        let mut result = Ok(());
+        self.pixel_buffer_update(|bytes: &mut [u8], pitch: usize| {
+            let (prefix, pixels, suffix) = unsafe { bytes.align_to_mut::<[u8; 4]>() };
+            if !prefix.is_empty() || !suffix.is_empty() {
+                result = Err(misaligned_pixels());
+                return;
             }
-            Scaling::StretchToWindow => None,
+            func(pixels, pitch / 4);
+        })?;
+        result
+    }
+
+    /// Same as "pixel_buffer_update", but provides a FrameBuffer with bounds checked pixel
+    /// access in the pixel buffer format. Not available in indexed mode.
+    pub fn pixel_buffer_frame<F, R>(&mut self, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut FrameBuffer) -> R,
+    {
+        if self.indices.is_some() {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "FrameBuffer is not available in indexed mode",
+            )));
         }
+        let (width, height) = (self.width, self.height);
+        // The post-processing source is always RGB24
+        let format = match &self.post_process {
+            Some(post) if post.uses_source(self.pixel_buffer_format) => PixelFormat::RGB24,
+            _ => self.pixel_buffer_format,
+        };
+        self.pixel_buffer_update(|pixels: &mut [u8], pitch: usize| {
+            func(&mut FrameBuffer::new(pixels, pitch, width, height, format))
+        })
     }
 
-    /// Presents the current pixel buffer respecting the scaling strategy.
-    pub fn pixel_buffer_present(&mut self) -> SdlResult<()> {
-        let rect = self.get_scaled_rect().unwrap(); // TODO: Clean up unwrap
-        let Some(buffer) = &self.pixel_buffer else {
+    fn check_32_bit_format(&self) -> SdlResult<()> {
+        if self.indices.is_some() || self.pixel_buffer_format.bytes_per_pixel() != 4 {
             return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Pixel buffer not initialized",
+                std::io::ErrorKind::InvalidInput,
+                "Pixel buffer format is not 32 bits per pixel",
             )));
+        }
+        Ok(())
+    }
+
+    /// Where the render target is currently drawn in the window, with coordinate conversions.
+    pub fn viewport(&self) -> Viewport {
+        Viewport::new(
+            self.get_scaled_rect(),
+            self.width,
+            self.height,
+            self.dpi_mult,
+        )
+    }
+
+    fn get_scaled_rect(&self) -> Rect {
+        scaling::scaled_rect(
+            &self.scaling,
+            self.width,
+            self.height,
+            self.canvas.window().size(),
+            self.dpi_mult,
+        )
+    }
+
+    // Where the frame is presented, after the transform's offset and zoom, and the transform
+    // with the screen shake. Shared by everything drawn with the frame, so it all moves together.
+    fn presented_dest(&self) -> (FRect, Transform) {
+        let transform = self.transform.with_shake(&self.screen_shake);
+        let dest = transform.apply(self.get_scaled_rect(), (self.width, self.height));
+        (dest, transform)
+    }
+
+    /// Presents the current pixel buffer respecting the scaling strategy.
+    pub fn pixel_buffer_present(&mut self) -> SdlResult<()> {
+        let (dest, transform) = self.presented_dest();
+        let filter = self.filters.get(&self.scaling);
+        self.convert_indexed()?;
+        let (buffer, size) = match &mut self.post_process {
+            Some(post) if post.uses_source(self.pixel_buffer_format) => {
+                post.resize(self.width, self.height);
+                let size = post.output_size();
+                (post.process(&mut self.canvas)?, size)
+            }
+            _ => (
+                initialized(&mut self.pixel_buffer, "Pixel buffer")?,
+                (self.width, self.height),
+            ),
         };
-        self.canvas
-            .copy_ex(buffer, None, rect, 0.0, None, false, false)?;
+        letterbox::draw_letterbox(
+            &mut self.canvas,
+            &mut self.letterbox,
+            buffer,
+            (self.width, self.height),
+            dest,
+            &transform,
+            &mut self.letterbox_blur,
+        )?;
+        filter::copy_filtered(
+            &mut self.canvas,
+            buffer,
+            size,
+            dest,
+            filter,
+            &mut self.prescaler,
+            &transform,
+        )?;
+        if let Some(transition) = &mut self.transition {
+            transition.draw(&mut self.canvas, buffer, size, dest, filter, &transform)?;
+        }
+        if let Some(capture) = &mut self.capture {
+            capture.grab_frame(&mut self.canvas, buffer, size)?;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.grab_frame(&mut self.canvas, buffer, size)?;
+        }
+        transition::snapshot(&mut self.canvas, buffer, size, &mut self.last_frame)?;
+        Ok(())
+    }
+
+    // Converts the indices to RGB using the palette, writing into the pixel buffer
+    // (or the post-processing source, if enabled).
+    fn convert_indexed(&mut self) -> SdlResult<()> {
+        let Some(indices) = &self.indices else {
+            return Ok(());
+        };
+        let width = self.width as usize;
+        match &mut self.post_process {
+            // The indexed pixel buffer is always RGB24.
+            Some(post) if post.uses_source(self.pixel_buffer_format) => {
+                post.resize(self.width, self.height);
+                let (rgb, pitch) = post.source_mut();
+                self.palette.convert(indices, width, rgb, pitch);
+            }
+            _ => {
+                if let Some(buffer) = &mut self.pixel_buffer {
+                    let palette = &self.palette;
+                    buffer.with_lock(None, |rgb: &mut [u8], pitch: usize| {
+                        palette.convert(indices, width, rgb, pitch)
+                    })?;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -561,18 +1176,267 @@ impl App {
     /// Warning: can be much slower than "pixel_buffer_present" if the goal is to simply
     /// draw pixel-by-pixel.
     pub fn render_target_present(&mut self) -> SdlResult<()> {
-        let rect = self.get_scaled_rect().unwrap(); // TODO: Clean up unwrap
-        let Some(target) = &self.render_target else {
+        let (dest, transform) = self.presented_dest();
+        let filter = self.filters.get(&self.scaling);
+        let target = initialized(&mut self.render_target, "Render Target")?;
+        letterbox::draw_letterbox(
+            &mut self.canvas,
+            &mut self.letterbox,
+            target,
+            (self.width, self.height),
+            dest,
+            &transform,
+            &mut self.letterbox_blur,
+        )?;
+        filter::copy_filtered(
+            &mut self.canvas,
+            target,
+            (self.width, self.height),
+            dest,
+            filter,
+            &mut self.prescaler,
+            &transform,
+        )?;
+        if let Some(transition) = &mut self.transition {
+            let size = (self.width, self.height);
+            transition.draw(&mut self.canvas, target, size, dest, filter, &transform)?;
+        }
+        if let Some(capture) = &mut self.capture {
+            capture.grab_frame(&mut self.canvas, target, (self.width, self.height))?;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.grab_frame(&mut self.canvas, target, (self.width, self.height))?;
+        }
+        let size = (self.width, self.height);
+        transition::snapshot(&mut self.canvas, target, size, &mut self.last_frame)?;
+        Ok(())
+    }
+
+    /// Starts a transition from the last presented frame (pixel buffer, render target or layers,
+    /// after post-processing) to the frames presented from now on, lasting "duration" seconds of
+    /// "elapsed_time". Usually called right when switching scenes, even after drawing the new
+    /// scene, since a copy of each frame is kept when presenting. Replaces any running
+    /// transition. Does nothing if no frame was presented yet.
+    pub fn transition_start(&mut self, transition: Transition, duration: f64) -> SdlResult<()> {
+        let Some(size) = self.last_frame.size() else {
+            return Ok(());
+        };
+        // The next present takes a new snapshot.
+        let outgoing = std::mem::take(&mut self.last_frame);
+        let state = TransitionState::new(transition, duration, outgoing, size);
+        if let Some(previous) = self.transition.replace(state) {
+            previous.destroy();
+        }
+        Ok(())
+    }
+
+    /// True while a transition is running.
+    pub fn transition_active(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// How far the running transition is, from 0.0 to 1.0, or None if there's no transition.
+    /// Useful to swap scenes halfway through a "Transition::Fade".
+    pub fn transition_progress(&self) -> Option<f64> {
+        self.transition
+            .as_ref()
+            .map(|transition| transition.progress())
+    }
+
+    /// Creates a named layer with its own resolution, in RGBA32 format so it can be
+    /// transparent. Replaces any existing layer with the same name. See "present_layers".
+    pub fn layer_create(
+        &mut self,
+        name: &str,
+        kind: LayerKind,
+        width: u32,
+        height: u32,
+    ) -> SdlResult<()> {
+        self.layer_create_format(name, kind, width, height, PixelFormat::RGBA32)
+    }
+
+    /// Creates a named layer with its own resolution, using any of the available pixel formats.
+    /// Replaces any existing layer with the same name.
+    pub fn layer_create_format(
+        &mut self,
+        name: &str,
+        kind: LayerKind,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> SdlResult<()> {
+        let layer = Layer::new(&mut self.canvas, name, kind, width, height, format)?;
+        match self.layers.iter().position(|layer| layer.name() == name) {
+            Some(index) => std::mem::replace(&mut self.layers[index], layer).destroy(),
+            None => self.layers.push(layer),
+        }
+        Ok(())
+    }
+
+    /// Finds a layer by name.
+    pub fn layer(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name() == name)
+    }
+
+    /// Iterates over all layers, in creation order.
+    pub fn layers(&mut self) -> impl Iterator<Item = &mut Layer> {
+        self.layers.iter_mut()
+    }
+
+    /// Removes a layer and frees its texture.
+    pub fn layer_remove(&mut self, name: &str) {
+        if let Some(index) = self.layers.iter().position(|layer| layer.name() == name) {
+            self.layers.remove(index).destroy();
+        }
+    }
+
+    /// Draws into a render target layer with regular SDL canvas functions.
+    pub fn layer_canvas<F>(&mut self, name: &str, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut Canvas<Window>),
+    {
+        let Some(layer) = self.layers.iter_mut().find(|layer| layer.name() == name) else {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
-                "Render Target not initialized",
+                format!("Layer \"{}\" not found", name),
             )));
         };
-        self.canvas
-            .copy_ex(target, None, rect, 0.0, None, false, false)?;
+        if layer.kind() != LayerKind::RenderTarget {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Layer is not a render target, use Layer::pixel_buffer_update instead",
+            )));
+        }
+        self.canvas.with_texture_canvas(&mut layer.texture, func)?;
+        Ok(())
+    }
+
+    /// Composites all visible layers in z order, respecting the scaling strategy. Replaces
+    /// "pixel_buffer_present" and "render_target_present" when using layers. The layers are
+    /// first composited into one frame at the size they're presented at, which is what
+    /// captures, recordings and transitions use.
+    pub fn present_layers(&mut self) -> SdlResult<()> {
+        let (dest, transform) = self.presented_dest();
+        let mut order: Vec<usize> = (0..self.layers.len())
+            .filter(|i| self.layers[*i].visible)
+            .collect();
+        // Stable, so layers with the same z keep their creation order.
+        order.sort_by_key(|i| self.layers[*i].z);
+        // Scaled like the App's resolution, which "dest" is based on, even if the bottom
+        // layer has a different one.
+        if let Some(bottom) = order.first() {
+            letterbox::draw_letterbox(
+                &mut self.canvas,
+                &mut self.letterbox,
+                &self.layers[*bottom].texture,
+                (self.width, self.height),
+                dest,
+                &transform,
+                &mut self.letterbox_blur,
+            )?;
+        }
+
+        // Composited into a single frame at the presented size, over the background color,
+        // so it can be captured, recorded and used by transitions like the other presents.
+        let size = (
+            (dest.w.round() as u32).max(1),
+            (dest.h.round() as u32).max(1),
+        );
+        let area = FRect::new(0.0, 0.0, size.0 as f32, size.1 as f32);
+        for &i in &order {
+            self.layers[i].prepare(&mut self.canvas, area)?;
+        }
+        let frame = self.layers_frame.get(&mut self.canvas, size.0, size.1)?;
+        let (layers, bg_color) = (&mut self.layers, self.bg_color);
+        let mut result = Ok(());
+        self.canvas.with_texture_canvas(frame, |target| {
+            target.set_draw_color(bg_color);
+            target.clear();
+            target.set_draw_color((255, 255, 255, 255));
+            for &i in &order {
+                result = layers[i].composite(target, area, &Transform::default());
+                if result.is_err() {
+                    break;
+                }
+            }
+        })?;
+        result?;
+        // Each layer was already filtered, and the frame is opaque.
+        frame.set_scale_mode(ScaleMode::Nearest);
+        frame.set_blend_mode(BlendMode::None);
+        transform.copy(&mut self.canvas, frame, None, dest, dest)?;
+
+        if let Some(transition) = &mut self.transition {
+            let filter = Filter::Nearest;
+            transition.draw(&mut self.canvas, frame, size, dest, filter, &transform)?;
+        }
+        if let Some(capture) = &mut self.capture {
+            capture.grab_frame(&mut self.canvas, frame, size)?;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.grab_frame(&mut self.canvas, frame, size)?;
+        }
+        transition::snapshot(&mut self.canvas, frame, size, &mut self.last_frame)?;
+        Ok(())
+    }
+
+    /// Starts recording every frame to "path", an animated GIF file or a folder for image
+    /// sequences, timed with "elapsed_time". Replaces any running capture, which is finished
+    /// first. Frames are encoded and written on a background thread, and "frame_finish" only
+    /// waits for it when it falls 8 frames behind. Call "capture_stop" to finish writing
+    /// the files.
+    pub fn capture_start<P>(&mut self, path: P, options: CaptureOptions) -> SdlResult<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        self.capture_stop()?;
+        self.capture = Some(Capture::new(path.as_ref(), options)?);
+        Ok(())
+    }
+
+    /// Stops recording and finishes writing the files, if capturing.
+    pub fn capture_stop(&mut self) -> SdlResult<()> {
+        if let Some(capture) = self.capture.take() {
+            capture.finish()?;
+        }
         Ok(())
     }
 
+    /// True while recording.
+    pub fn capture_active(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Starts recording every frame of the pixel buffer, render target or layers to "path" as Y4M
+    /// video at a constant "fps", and the samples sent to "audio_push_samples" to a WAV
+    /// file with the same name, if audio was initialized. The WAV file always lasts exactly
+    /// as many frames as the video, at 1 / "fps" seconds each, so both stay in sync by frame
+    /// number regardless of the real frame rate: missing audio is padded with silence, and
+    /// audio pushed ahead is held for the next frames, dropping anything more than 2 frames
+    /// ahead. Frames without a present repeat the last one. Replaces any running recording,
+    /// which is finished first.
+    pub fn record_start<P>(&mut self, path: P, fps: f64) -> SdlResult<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        self.record_stop()?;
+        self.recorder = Some(Recorder::new(path.as_ref(), fps, self.sample_rate)?);
+        Ok(())
+    }
+
+    /// Stops recording and finishes writing the files, if recording.
+    pub fn record_stop(&mut self) -> SdlResult<()> {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish()?;
+        }
+        Ok(())
+    }
+
+    /// True while recording.
+    pub fn record_active(&self) -> bool {
+        self.recorder.is_some()
+    }
+
     /// Required to be called at the end of a frame loop. Presents the canvas and performs an idle wait
     /// if frame rate limiting is required. Ironically, performing this idle loop may *lower* the CPU
     /// use in some platforms, compared to pure VSync!
@@ -588,26 +1452,30 @@ impl App {
         {
             if self.display_overlay {
                 if let Some(font) = &mut self.default_font {
-                    // self.canvas.set_draw_color((255, 255, 255, 255));
-                    let mut y = self.overlay_coords.y;
-                    for line in self.overlay.drain(..) {
-                        font.draw(
-                            line,
-                            self.overlay_coords.x,
-                            y,
-                            self.overlay_scale,
-                            &mut self.canvas,
-                        )?;
-                        let inc =
-                            (font.height() as f32 * self.overlay_line_spacing) * self.overlay_scale;
-                        y += (inc * font.line_spacing) as i32;
-                    }
+                    font_atlas::draw_overlay(
+                        font,
+                        &mut self.overlay,
+                        self.overlay_coords,
+                        self.overlay_scale,
+                        self.overlay_line_spacing,
+                        &mut self.canvas,
+                    )?;
                 }
             }
         }
 
+        if let Some(capture) = &mut self.capture {
+            capture.frame_finish(&mut self.canvas, self.elapsed_time)?;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.frame_finish()?;
+        }
+
         // TESTING: Moved here from end of function, right before the "Ok(())"
         self.canvas.present();
+        for tool_window in &mut self.tool_windows {
+            tool_window.frame_finish()?;
+        }
 
         match self.timing {
             // Optional FPS limiting
@@ -702,6 +1570,9 @@ impl App {
     /// Copies a slice of StereoFrames to the audio buffer. Ideally you should call this only once per frame,
     /// with all the samples that you need for that frame.
     pub fn audio_push_samples(&mut self, samples: &[i16]) -> SdlResult<()> {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push_samples(samples);
+        }
         let Some(stream) = &mut self.audio_stream else {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
@@ -762,6 +1633,84 @@ pub(crate) fn quantize(value: f64, size: f64) -> f64 {
     }
 }
 
+// Creates a streaming texture for a pixel buffer, or a target texture for a render target.
+// Shared by the App, layers and tool windows.
+pub(crate) fn create_texture(
+    canvas: &mut Canvas<Window>,
+    kind: LayerKind,
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+) -> SdlResult<Texture> {
+    Ok(match kind {
+        LayerKind::PixelBuffer => {
+            canvas.create_texture_streaming(format.to_sdl(), width, height)?
+        }
+        LayerKind::RenderTarget => canvas.create_texture_target(format.to_sdl(), width, height)?,
+    })
+}
+
+// Same as "create_texture", destroying the texture it replaces in "slot", if any.
+pub(crate) fn replace_texture(
+    canvas: &mut Canvas<Window>,
+    slot: &mut Option<Texture>,
+    kind: LayerKind,
+    format: PixelFormat,
+    width: u32,
+    height: u32,
+) -> SdlResult<()> {
+    if let Some(texture) = slot.take() {
+        // Required with "unsafe_textures".
+        unsafe { texture.destroy() };
+    }
+    *slot = Some(create_texture(canvas, kind, format, width, height)?);
+    Ok(())
+}
+
+// The texture in "slot", or an error saying "name" is not initialized.
+pub(crate) fn initialized<'a>(
+    slot: &'a mut Option<Texture>,
+    name: &str,
+) -> SdlResult<&'a mut Texture> {
+    match slot {
+        Some(texture) => Ok(texture),
+        None => Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not initialized", name),
+        ))),
+    }
+}
+
+// Locked pixels that can't be viewed as whole 32 bit pixels. SDL aligns them, so this
+// shouldn't happen.
+fn misaligned_pixels() -> Box<dyn std::error::Error> {
+    Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "Pixel buffer memory is not aligned to 32 bit pixels",
+    ))
+}
+
+// Returns a copy of RGBA pixels scaled by an integer factor using nearest neighbor.
+pub(crate) fn scale_rgba(rgba: &[u8], width: u32, height: u32, scale: u32) -> SdlResult<Vec<u8>> {
+    let (width, height, scale) = (width as usize, height as usize, scale as usize);
+    if rgba.len() < width * height * 4 {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "RGBA slice is smaller than width * height * 4",
+        )));
+    }
+    let new_width = width * scale;
+    let mut result = vec![0u8; new_width * height * scale * 4];
+    for y in 0..height * scale {
+        for x in 0..new_width {
+            let src = ((y / scale) * width + (x / scale)) * 4;
+            let dst = (y * new_width + x) * 4;
+            result[dst..dst + 4].copy_from_slice(&rgba[src..src + 4]);
+        }
+    }
+    Ok(result)
+}
+
 #[allow(unused)]
 pub(crate) fn next_power_of_two(mut n: u32) -> u32 {
     if n.is_power_of_two() {
@@ -789,3 +1738,43 @@ pub(crate) fn next_power_of_two(mut n: u32) -> u32 {
 //     x |= x >> 16;
 //     (x >> 1) + 1
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "opens a window"]
+    fn resize_keeps_indexed_mode() {
+        let scaling = Scaling::Expand {
+            min_width: 64,
+            min_height: 48,
+        };
+        let mut app = App::new("test", 64, 48, Timing::default(), scaling).unwrap();
+        app.init_pixel_buffer_indexed().unwrap();
+        app.set_size(80, 48).unwrap();
+        assert!(app.pixel_buffer_is_indexed());
+        assert_eq!(app.indices.as_ref().unwrap().len(), 80 * 48);
+    }
+
+    #[test]
+    #[ignore = "opens a window"]
+    fn present_layers_feeds_capture() {
+        let mut app = App::new("test", 64, 48, Timing::default(), Scaling::Integer).unwrap();
+        app.layer_create("background", LayerKind::PixelBuffer, 64, 48)
+            .unwrap();
+        let path = std::env::temp_dir().join("mini_sdl_layers_capture_test");
+        let options = CaptureOptions {
+            format: CaptureFormat::RawSequence,
+            ..Default::default()
+        };
+        app.capture_start(&path, options).unwrap();
+        app.frame_start().unwrap();
+        app.present_layers().unwrap();
+        app.frame_finish().unwrap();
+        app.capture_stop().unwrap();
+        let list = std::fs::read_to_string(path.join("frames.txt")).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        assert_eq!(list.lines().count(), 1);
+    }
+}
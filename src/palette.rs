@@ -0,0 +1,67 @@
+use std::ops::Range;
+
+/// A 256 color palette used by the indexed pixel buffer (see "App::init_pixel_buffer_indexed").
+/// Can be freely modified every frame, which is how effects like palette cycling and fades
+/// are usually done with indexed color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// RGB colors, one per index.
+    pub colors: [(u8, u8, u8); 256],
+}
+
+impl Default for Palette {
+    /// A grayscale palette, from black at index 0 to white at index 255.
+    fn default() -> Self {
+        let mut colors = [(0, 0, 0); 256];
+        for (i, color) in colors.iter_mut().enumerate() {
+            *color = (i as u8, i as u8, i as u8);
+        }
+        Self { colors }
+    }
+}
+
+impl Palette {
+    /// Creates a palette from a list of colors. Indices beyond the list are black.
+    pub fn from_colors(list: &[(u8, u8, u8)]) -> Self {
+        let mut colors = [(0, 0, 0); 256];
+        for (color, new) in colors.iter_mut().zip(list) {
+            *color = *new;
+        }
+        Self { colors }
+    }
+
+    /// Shifts the colors within "range" by "amount" positions, wrapping around.
+    /// Calling it every few frames produces classic palette cycling effects like water and fire.
+    pub fn cycle(&mut self, range: Range<usize>, amount: i32) {
+        let (start, end) = (range.start.min(256), range.end.min(256));
+        if start >= end {
+            return;
+        }
+        let slice = &mut self.colors[start..end];
+        let amount = amount.rem_euclid(slice.len() as i32) as usize;
+        slice.rotate_right(amount);
+    }
+
+    /// Returns a copy of this palette blended towards "color" by "amount" (0.0 to 1.0).
+    /// Useful for fades to black, white or any other color.
+    pub fn faded(&self, color: (u8, u8, u8), amount: f32) -> Palette {
+        let amount = amount.clamp(0.0, 1.0);
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount) as u8;
+        let mut result = self.clone();
+        for c in result.colors.iter_mut() {
+            *c = (lerp(c.0, color.0), lerp(c.1, color.1), lerp(c.2, color.2));
+        }
+        result
+    }
+
+    // Converts indices to RGB, writing "width" pixels per row into "rgb" using "pitch" bytes per row.
+    pub(crate) fn convert(&self, indices: &[u8], width: usize, rgb: &mut [u8], pitch: usize) {
+        for (y, row) in indices.chunks_exact(width).enumerate() {
+            let dest = &mut rgb[y * pitch..y * pitch + width * 3];
+            for (pixel, index) in dest.chunks_exact_mut(3).zip(row) {
+                let (r, g, b) = self.colors[*index as usize];
+                pixel.copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+}
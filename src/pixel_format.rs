@@ -0,0 +1,106 @@
+use sdl3::{pixels::Color, sys::pixels::SDL_PixelFormat};
+
+/// The pixel formats available for the pixel buffer and render target.
+/// The best performing format varies per platform and renderer, 32 bit formats
+/// are usually the fastest on modern GPUs.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum PixelFormat {
+    #[default]
+    /// 3 bytes per pixel, in R, G, B order.
+    RGB24,
+
+    /// 4 bytes per pixel, in R, G, B, A order regardless of the platform's endianness.
+    RGBA32,
+
+    /// One u32 per pixel, packed as 0xAARRGGBB.
+    ARGB8888,
+
+    /// One u16 per pixel, packed as 5 bits of red, 6 of green and 5 of blue.
+    RGB565,
+
+    /// One u32 per pixel, packed as 0xXXRRGGBB. The highest byte is ignored.
+    XRGB8888,
+}
+
+impl PixelFormat {
+    /// How many bytes each pixel takes.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::RGB24 => 3,
+            PixelFormat::RGB565 => 2,
+            PixelFormat::RGBA32 | PixelFormat::ARGB8888 | PixelFormat::XRGB8888 => 4,
+        }
+    }
+
+    /// True if the format stores alpha.
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, PixelFormat::RGBA32 | PixelFormat::ARGB8888)
+    }
+
+    /// The equivalent SDL pixel format.
+    pub fn to_sdl(&self) -> sdl3::pixels::PixelFormat {
+        let format = match self {
+            PixelFormat::RGB24 => SDL_PixelFormat::RGB24,
+            PixelFormat::RGBA32 => SDL_PixelFormat::RGBA32,
+            PixelFormat::ARGB8888 => SDL_PixelFormat::ARGB8888,
+            PixelFormat::RGB565 => SDL_PixelFormat::RGB565,
+            PixelFormat::XRGB8888 => SDL_PixelFormat::XRGB8888,
+        };
+        unsafe { sdl3::pixels::PixelFormat::from_ll(format) }
+    }
+
+    // Writes a color to the bytes of a single pixel in this format.
+    pub(crate) fn write(&self, color: Color, bytes: &mut [u8]) {
+        match self {
+            PixelFormat::RGB24 => bytes[..3].copy_from_slice(&[color.r, color.g, color.b]),
+            PixelFormat::RGBA32 => {
+                bytes[..4].copy_from_slice(&[color.r, color.g, color.b, color.a])
+            }
+            PixelFormat::ARGB8888 | PixelFormat::XRGB8888 => {
+                let alpha = if *self == PixelFormat::ARGB8888 {
+                    color.a
+                } else {
+                    255
+                };
+                let value = u32::from_be_bytes([alpha, color.r, color.g, color.b]);
+                bytes[..4].copy_from_slice(&value.to_ne_bytes());
+            }
+            PixelFormat::RGB565 => {
+                let value = ((color.r as u16 >> 3) << 11)
+                    | ((color.g as u16 >> 2) << 5)
+                    | (color.b as u16 >> 3);
+                bytes[..2].copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+    }
+
+    // Reads the color of a single pixel in this format.
+    pub(crate) fn read(&self, bytes: &[u8]) -> Color {
+        match self {
+            PixelFormat::RGB24 => Color::RGB(bytes[0], bytes[1], bytes[2]),
+            PixelFormat::RGBA32 => Color::RGBA(bytes[0], bytes[1], bytes[2], bytes[3]),
+            PixelFormat::ARGB8888 | PixelFormat::XRGB8888 => {
+                let value = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                let [a, r, g, b] = value.to_be_bytes();
+                let a = if *self == PixelFormat::ARGB8888 {
+                    a
+                } else {
+                    255
+                };
+                Color::RGBA(r, g, b, a)
+            }
+            PixelFormat::RGB565 => {
+                let value = u16::from_ne_bytes([bytes[0], bytes[1]]);
+                let r = ((value >> 11) & 0x1F) as u8;
+                let g = ((value >> 5) & 0x3F) as u8;
+                let b = (value & 0x1F) as u8;
+                // Expands to 8 bits, replicating the high bits so white stays white.
+                Color::RGB(
+                    (r << 3) | (r >> 2),
+                    (g << 2) | (g >> 4),
+                    (b << 3) | (b >> 2),
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::SdlResult;
+use sdl3::{
+    pixels::PixelFormat,
+    render::{Canvas, Texture},
+    sys::pixels::SDL_PixelFormat,
+    video::Window,
+};
+
+/// A CPU post-processing effect. Effects run in order, at the upscaled resolution
+/// of the PostProcess chain, so they can draw details smaller than a pixel buffer pixel.
+/// Intensities range from 0.0 (no effect) to 1.0 (strongest).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Effect {
+    /// Darkens the bottom row of each pixel, like the gaps between CRT scanlines.
+    Scanlines { intensity: f32 },
+
+    /// Vertical red, green and blue stripes, like a Trinitron CRT.
+    ApertureGrille { intensity: f32 },
+
+    /// Bright areas bleed light into their surroundings. "threshold" is the minimum
+    /// brightness (0 to 255) that produces bloom.
+    Bloom { threshold: u8, intensity: f32 },
+
+    /// Blends each frame with the previous ones, like slow LCDs and phosphor persistence.
+    /// Higher values leave longer trails.
+    Ghosting { persistence: f32 },
+
+    /// Darkens the borders of each pixel in both axis, like handheld LCD screens.
+    LcdGrid { intensity: f32 },
+}
+
+/// A chain of CPU effects applied between "pixel_buffer_update" and "pixel_buffer_present".
+/// Initialize it with "App::post_process_init". While enabled, "pixel_buffer_update" provides
+/// a CPU-side RGB buffer instead of the pixel buffer texture, which is then upscaled,
+/// processed and presented. Effects can be changed at any time.
+pub struct PostProcess {
+    /// The effects, applied in order.
+    pub effects: Vec<Effect>,
+    /// Turns the whole chain on or off without losing its settings.
+    pub enabled: bool,
+    scale: u32,
+    width: u32,
+    height: u32,
+    source: Vec<u8>,
+    output: Vec<u8>,
+    previous: Vec<u8>,
+    bloom: Vec<u8>,
+    texture: Option<Texture>,
+}
+
+impl PostProcess {
+    pub(crate) fn new(scale: u32) -> Self {
+        Self {
+            effects: Vec::new(),
+            enabled: true,
+            scale: scale.max(1),
+            width: 0,
+            height: 0,
+            source: Vec::new(),
+            output: Vec::new(),
+            previous: Vec::new(),
+            bloom: Vec::new(),
+            texture: None,
+        }
+    }
+
+    /// How many times larger than the pixel buffer the processed image is.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    // Resizes the internal buffers to match the pixel buffer, if needed.
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        let out_len = (width * self.scale * height * self.scale * 3) as usize;
+        self.source = vec![0; (width * height * 3) as usize];
+        self.output = vec![0; out_len];
+        self.previous = vec![0; out_len];
+        self.bloom = vec![0; out_len];
+        if let Some(texture) = self.texture.take() {
+            // Required with "unsafe_textures".
+            unsafe { texture.destroy() };
+        }
+    }
+
+    // True if a pixel buffer in "format" is drawn to the source. Only RGB24 is, other formats
+    // are presented without effects.
+    pub(crate) fn uses_source(&self, format: crate::PixelFormat) -> bool {
+        self.enabled && format == crate::PixelFormat::RGB24
+    }
+
+    // The RGB buffer the user draws to, and its pitch.
+    pub(crate) fn source_mut(&mut self) -> (&mut [u8], usize) {
+        (&mut self.source, self.width as usize * 3)
+    }
+
+    // The size of the processed image.
+    pub(crate) fn output_size(&self) -> (u32, u32) {
+        (self.width * self.scale, self.height * self.scale)
+    }
+
+    // Upscales the source, runs all effects and uploads the result to a texture.
+    pub(crate) fn process(&mut self, canvas: &mut Canvas<Window>) -> SdlResult<&mut Texture> {
+        self.apply_effects();
+        let (out_width, out_height) = self.output_size();
+        let ow = out_width as usize;
+        if self.texture.is_none() {
+            self.texture = Some(canvas.create_texture_streaming(
+                unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) },
+                out_width,
+                out_height,
+            )?);
+        }
+        let texture = self.texture.as_mut().unwrap();
+        let output = &self.output;
+        texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+            for (y, row) in output.chunks_exact(ow * 3).enumerate() {
+                buffer[y * pitch..y * pitch + ow * 3].copy_from_slice(row);
+            }
+        })?;
+        Ok(texture)
+    }
+
+    // Upscales the source into the output and runs all effects on it.
+    fn apply_effects(&mut self) {
+        let (out_width, out_height) = self.output_size();
+        let (ow, oh) = (out_width as usize, out_height as usize);
+        let (width, scale) = (self.width as usize, self.scale as usize);
+
+        // Nearest neighbor upscale
+        for y in 0..oh {
+            let src_row = (y / scale) * width * 3;
+            let dst_row = y * ow * 3;
+            for x in 0..ow {
+                let src = src_row + (x / scale) * 3;
+                let dst = dst_row + x * 3;
+                self.output[dst..dst + 3].copy_from_slice(&self.source[src..src + 3]);
+            }
+        }
+
+        for effect in &self.effects {
+            match *effect {
+                Effect::Scanlines { intensity } => {
+                    let mult = 1.0 - intensity.clamp(0.0, 1.0);
+                    for y in 0..oh {
+                        // At 1x there's no room inside each pixel, so every other row is used.
+                        let is_gap = if scale == 1 {
+                            y % 2 == 1
+                        } else {
+                            y % scale == scale - 1
+                        };
+                        if is_gap {
+                            darken(&mut self.output[y * ow * 3..(y + 1) * ow * 3], mult);
+                        }
+                    }
+                }
+                Effect::ApertureGrille { intensity } => {
+                    let mult = 1.0 - intensity.clamp(0.0, 1.0);
+                    for pixel in 0..ow * oh {
+                        // Each column keeps only one of the channels at full strength.
+                        let keep = (pixel % ow) % 3;
+                        for channel in 0..3 {
+                            if channel != keep {
+                                let i = pixel * 3 + channel;
+                                self.output[i] = (self.output[i] as f32 * mult) as u8;
+                            }
+                        }
+                    }
+                }
+                Effect::Bloom {
+                    threshold,
+                    intensity,
+                } => {
+                    // Bright pass
+                    for (bloom, value) in self.bloom.iter_mut().zip(&self.output) {
+                        *bloom = if *value > threshold { *value } else { 0 };
+                    }
+                    // Blur radius grows with the scale so the glow looks similar on all scales.
+                    let radius = scale * 2;
+                    box_blur(&mut self.bloom, ow, oh, radius, true);
+                    box_blur(&mut self.bloom, ow, oh, radius, false);
+                    let intensity = intensity.clamp(0.0, 1.0);
+                    for (value, bloom) in self.output.iter_mut().zip(&self.bloom) {
+                        *value = (*value as f32 + *bloom as f32 * intensity).min(255.0) as u8;
+                    }
+                }
+                Effect::Ghosting { persistence } => {
+                    let persistence = persistence.clamp(0.0, 1.0);
+                    for (value, previous) in self.output.iter_mut().zip(&mut self.previous) {
+                        let blended =
+                            *value as f32 * (1.0 - persistence) + *previous as f32 * persistence;
+                        *value = blended as u8;
+                        *previous = *value;
+                    }
+                }
+                Effect::LcdGrid { intensity } => {
+                    if scale < 2 {
+                        continue; // No room for a grid
+                    }
+                    let mult = 1.0 - intensity.clamp(0.0, 1.0);
+                    for y in 0..oh {
+                        let row = &mut self.output[y * ow * 3..(y + 1) * ow * 3];
+                        if y % scale == scale - 1 {
+                            darken(row, mult);
+                        } else {
+                            for x in (scale - 1..ow).step_by(scale) {
+                                darken(&mut row[x * 3..x * 3 + 3], mult);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn darken(pixels: &mut [u8], mult: f32) {
+    for value in pixels {
+        *value = (*value as f32 * mult) as u8;
+    }
+}
+
+// A simple separable box blur on RGB data, one axis at a time.
+fn box_blur(pixels: &mut [u8], width: usize, height: usize, radius: usize, horizontal: bool) {
+    let (len, lines) = if horizontal {
+        (width, height)
+    } else {
+        (height, width)
+    };
+    let index = |line: usize, i: usize| {
+        if horizontal {
+            (line * width + i) * 3
+        } else {
+            (i * width + line) * 3
+        }
+    };
+    let mut line_copy = vec![0u8; len * 3];
+    for line in 0..lines {
+        for i in 0..len {
+            let src = index(line, i);
+            line_copy[i * 3..i * 3 + 3].copy_from_slice(&pixels[src..src + 3]);
+        }
+        for i in 0..len {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius + 1).min(len);
+            let mut sum = [0u32; 3];
+            for pixel in line_copy[start * 3..end * 3].chunks_exact(3) {
+                for (total, value) in sum.iter_mut().zip(pixel) {
+                    *total += *value as u32;
+                }
+            }
+            let count = (end - start) as u32;
+            let dst = index(line, i);
+            for (value, total) in pixels[dst..dst + 3].iter_mut().zip(sum) {
+                *value = (total / count) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs "effects" on a "width" x "height" RGB source and returns the output.
+    fn run(effects: &[Effect], scale: u32, width: u32, height: u32, source: &[u8]) -> Vec<u8> {
+        let mut post = PostProcess::new(scale);
+        post.effects = effects.to_vec();
+        post.resize(width, height);
+        post.source_mut().0.copy_from_slice(source);
+        post.apply_effects();
+        post.output
+    }
+
+    #[test]
+    fn upscale_without_effects() {
+        let output = run(&[], 2, 2, 1, &[10, 20, 30, 40, 50, 60]);
+        let row = [10, 20, 30, 10, 20, 30, 40, 50, 60, 40, 50, 60];
+        assert_eq!(output, [row, row].concat());
+    }
+
+    #[test]
+    fn scanlines() {
+        let effect = Effect::Scanlines { intensity: 0.5 };
+        // The last row inside each scaled pixel is darkened.
+        let output = run(&[effect], 2, 1, 1, &[200, 100, 50]);
+        assert_eq!(
+            output,
+            [200, 100, 50, 200, 100, 50, 100, 50, 25, 100, 50, 25]
+        );
+        // Every other row at 1x.
+        let output = run(&[effect], 1, 1, 2, &[200, 100, 50, 200, 100, 50]);
+        assert_eq!(output, [200, 100, 50, 100, 50, 25]);
+    }
+
+    #[test]
+    fn aperture_grille() {
+        let effect = Effect::ApertureGrille { intensity: 1.0 };
+        let output = run(&[effect], 1, 3, 1, &[255; 9]);
+        assert_eq!(output, [255, 0, 0, 0, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bloom() {
+        let effect = Effect::Bloom {
+            threshold: 200,
+            intensity: 1.0,
+        };
+        // A single bright pixel glows over its neighbors, within a radius of 2 at 1x.
+        let mut source = [0; 15];
+        source[6..9].copy_from_slice(&[255, 255, 255]);
+        let output = run(&[effect], 1, 5, 1, &source);
+        let expected: Vec<u8> = [85, 63, 255, 63, 85]
+            .iter()
+            .flat_map(|&value| [value; 3])
+            .collect();
+        assert_eq!(output, expected);
+        // Nothing above the threshold, nothing changes.
+        let output = run(&[effect], 1, 5, 1, &[200; 15]);
+        assert_eq!(output, [200; 15]);
+    }
+
+    #[test]
+    fn ghosting() {
+        let mut post = PostProcess::new(1);
+        post.effects = vec![Effect::Ghosting { persistence: 0.5 }];
+        post.resize(1, 1);
+        post.source_mut().0.copy_from_slice(&[200, 100, 0]);
+        post.apply_effects();
+        assert_eq!(post.output, [100, 50, 0]);
+        post.apply_effects();
+        assert_eq!(post.output, [150, 75, 0]);
+    }
+
+    #[test]
+    fn lcd_grid() {
+        let effect = Effect::LcdGrid { intensity: 1.0 };
+        let output = run(&[effect], 2, 1, 1, &[100, 100, 100]);
+        assert_eq!(output, [100, 100, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        // No room for a grid at 1x.
+        let output = run(&[effect], 1, 1, 1, &[100, 100, 100]);
+        assert_eq!(output, [100, 100, 100]);
+    }
+
+    #[test]
+    fn box_blur_both_axis() {
+        // A single pixel in the middle of a 3x3 image, radius 1.
+        let mut pixels = [0; 27];
+        pixels[12..15].copy_from_slice(&[90, 90, 90]);
+        box_blur(&mut pixels, 3, 3, 1, true);
+        assert_eq!(&pixels[9..18], &[45, 45, 45, 30, 30, 30, 45, 45, 45]);
+        box_blur(&mut pixels, 3, 3, 1, false);
+        let expected: Vec<u8> = [22, 15, 22, 15, 10, 15, 22, 15, 22]
+            .iter()
+            .flat_map(|&value| [value; 3])
+            .collect();
+        assert_eq!(&pixels[..], &expected[..]);
+    }
+
+    #[test]
+    fn source_only_for_rgb24() {
+        use crate::PixelFormat::*;
+        let mut post = PostProcess::new(1);
+        assert!(post.uses_source(RGB24));
+        // The other formats keep their own texture, which a FrameBuffer reads in its format.
+        for format in [RGBA32, ARGB8888, RGB565, XRGB8888] {
+            assert!(!post.uses_source(format));
+        }
+        post.enabled = false;
+        assert!(!post.uses_source(RGB24));
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{
+    capture::{crop_or_pad, read_back},
+    filter::CachedTarget,
+    Image, SdlResult,
+};
+use sdl3::{
+    render::{Canvas, Texture},
+    video::Window,
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+// Stereo, like the stream opened by "App::audio_init".
+const CHANNELS: usize = 2;
+
+// How much audio pushed ahead of the video is held for the next frames, in video frames.
+// Older samples beyond that are dropped, so the audio can't drift away from the video.
+const MAX_HELD_FRAMES: f64 = 2.0;
+
+// A running recording started with "App::record_start". Writes one video frame per loop,
+// and exactly as much audio as the frame count lasts at the recording's frame rate.
+pub(crate) struct Recorder {
+    fps: f64,
+    video_path: PathBuf,
+    video: Option<Y4mWriter<BufWriter<File>>>,
+    target: CachedTarget,
+    pending: Option<Image>,
+    last: Option<Image>,
+    audio: Option<WavWriter<BufWriter<File>>>,
+    sample_rate: u32,
+    queue: Vec<i16>,
+    frame_count: u64,
+}
+
+impl Recorder {
+    // Writes the video to "path", and the audio next to it with the "wav" extension
+    // if "sample_rate" is known.
+    pub(crate) fn new(path: &Path, fps: f64, sample_rate: Option<u32>) -> SdlResult<Self> {
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Recording frame rate must be above zero",
+            )));
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let audio = match sample_rate {
+            Some(rate) => {
+                let file = BufWriter::new(File::create(path.with_extension("wav"))?);
+                Some(WavWriter::new(file, rate)?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            fps,
+            video_path: path.to_path_buf(),
+            video: None,
+            target: CachedTarget::default(),
+            pending: None,
+            last: None,
+            audio,
+            sample_rate: sample_rate.unwrap_or(0),
+            queue: Vec::new(),
+            frame_count: 0,
+        })
+    }
+
+    // Reads back "texture" (of "size" pixels) as the next video frame.
+    pub(crate) fn grab_frame(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        texture: &mut Texture,
+        size: (u32, u32),
+    ) -> SdlResult<()> {
+        self.pending = Some(read_back(canvas, &mut self.target, texture, size)?);
+        Ok(())
+    }
+
+    // Stores interleaved stereo samples until the end of the frame.
+    pub(crate) fn push_samples(&mut self, samples: &[i16]) {
+        if self.audio.is_some() {
+            self.queue.extend_from_slice(samples);
+        }
+    }
+
+    // Writes the frame grabbed since the last call, or repeats the previous one if nothing
+    // was presented, followed by the frame's audio. Nothing is written before the first frame.
+    pub(crate) fn frame_finish(&mut self) -> SdlResult<()> {
+        if let Some(image) = self.pending.take() {
+            self.last = Some(image);
+        }
+        let Some(image) = &self.last else {
+            return Ok(());
+        };
+        if self.video.is_none() {
+            let file = BufWriter::new(File::create(&self.video_path)?);
+            let (width, height) = (image.width(), image.height());
+            self.video = Some(Y4mWriter::new(file, width, height, self.fps)?);
+        }
+        let video = self.video.as_mut().unwrap();
+        if (image.width(), image.height()) == (video.width, video.height) {
+            video.write_frame(image)?;
+        } else {
+            video.write_frame(&crop_or_pad(image, video.width, video.height))?;
+        }
+        self.frame_count += 1;
+
+        if let Some(audio) = &mut self.audio {
+            let expected = audio_frames(self.frame_count, self.sample_rate, self.fps);
+            let missing = expected.saturating_sub(audio.frames) as usize;
+            let max_held = (self.sample_rate as f64 / self.fps * MAX_HELD_FRAMES).ceil();
+            align_audio(&mut self.queue, missing, max_held as usize);
+            audio.write_samples(&self.queue[..missing * CHANNELS])?;
+            self.queue.drain(..missing * CHANNELS);
+        }
+        Ok(())
+    }
+
+    // Finishes both files and frees the read back target.
+    pub(crate) fn finish(mut self) -> SdlResult<()> {
+        std::mem::take(&mut self.target).destroy();
+        if let Some(video) = self.video.take() {
+            video.finish()?;
+        }
+        if let Some(audio) = self.audio.take() {
+            audio.finish()?;
+        }
+        Ok(())
+    }
+}
+
+// How many stereo frames of audio "frame_count" video frames last.
+fn audio_frames(frame_count: u64, sample_rate: u32, fps: f64) -> u64 {
+    (frame_count as f64 * sample_rate as f64 / fps).round() as u64
+}
+
+// Makes sure "queue" holds at least "frames" stereo frames, padding with silence when the
+// audio is behind. What's left over after them is held for the next video frames, dropping
+// the oldest samples beyond "max_held" stereo frames.
+fn align_audio(queue: &mut Vec<i16>, frames: usize, max_held: usize) {
+    let needed = frames * CHANNELS;
+    if queue.len() < needed {
+        queue.resize(needed, 0);
+    }
+    let max_len = needed + max_held * CHANNELS;
+    if queue.len() > max_len {
+        // Whole stereo frames, so the channels stay in place.
+        let excess = (queue.len() - max_len).next_multiple_of(CHANNELS);
+        queue.drain(..excess);
+    }
+}
+
+// Writes uncompressed YUV 4:4:4 video with BT.601 limited range colors.
+struct Y4mWriter<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    fn new(mut writer: W, width: u32, height: u32, fps: f64) -> SdlResult<Self> {
+        // The frame rate as a fraction, precise to 1/1000 of a frame per second.
+        let (mut numerator, mut denominator) = ((fps * 1000.0).round() as u64, 1000u64);
+        let divisor = gcd(numerator, denominator);
+        numerator /= divisor;
+        denominator /= divisor;
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444",
+            width, height, numerator, denominator
+        )?;
+        Ok(Self {
+            writer,
+            width,
+            height,
+            planes: Vec::new(),
+        })
+    }
+
+    fn write_frame(&mut self, image: &Image) -> SdlResult<()> {
+        let count = (self.width * self.height) as usize;
+        self.planes.clear();
+        self.planes.resize(count * 3, 0);
+        let (y_plane, chroma) = self.planes.split_at_mut(count);
+        let (u_plane, v_plane) = chroma.split_at_mut(count);
+        for (i, pixel) in image.pixels().chunks_exact(4).enumerate() {
+            (y_plane[i], u_plane[i], v_plane[i]) = rgb_to_yuv(pixel[0], pixel[1], pixel[2]);
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> SdlResult<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a.max(1)
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Writes 16 bit stereo PCM. The sizes in the header are filled in by "finish".
+struct WavWriter<W: Write + Seek> {
+    writer: W,
+    frames: u64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    fn new(mut writer: W, sample_rate: u32) -> SdlResult<Self> {
+        let block_align = (CHANNELS * 2) as u16;
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?; // PCM
+        writer.write_all(&(CHANNELS as u16).to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&16u16.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        Ok(Self { writer, frames: 0 })
+    }
+
+    // Writes interleaved stereo samples.
+    fn write_samples(&mut self, samples: &[i16]) -> SdlResult<()> {
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.frames += (samples.len() / CHANNELS) as u64;
+        Ok(())
+    }
+
+    fn finish(mut self) -> SdlResult<W> {
+        let data_size = (self.frames * (CHANNELS * 2) as u64).min(u32::MAX as u64 - 36) as u32;
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&(36 + data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn wav_header() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 44100).unwrap();
+        wav.write_samples(&[1, -1, 2, -2]).unwrap();
+        let bytes = wav.finish().unwrap().into_inner();
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[4..8], &44u32.to_le_bytes());
+        assert_eq!(&bytes[24..28], &44100u32.to_le_bytes());
+        assert_eq!(&bytes[40..44], &8u32.to_le_bytes());
+        assert_eq!(&bytes[44..46], &1i16.to_le_bytes());
+    }
+
+    #[test]
+    fn y4m_frame() {
+        let mut y4m = Y4mWriter::new(Vec::new(), 2, 1, 59.94).unwrap();
+        let image = Image::from_rgba(2, 1, vec![0, 0, 0, 255, 255, 255, 255, 255]).unwrap();
+        y4m.write_frame(&image).unwrap();
+        let header = b"YUV4MPEG2 W2 H1 F2997:50 Ip A1:1 C444\nFRAME\n";
+        assert_eq!(&y4m.writer[..header.len()], header);
+        assert_eq!(&y4m.writer[header.len()..], &[16, 235, 128, 128, 128, 128]);
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mini_sdl_{}_{}", std::process::id(), name))
+    }
+
+    // Finishes "frames" video frames, pushing "samples(frame)" stereo frames of audio during
+    // each, and returns the WAV data size in stereo frames and the largest held queue.
+    fn record(name: &str, fps: f64, frames: u64, samples: impl Fn(u64) -> usize) -> (u64, usize) {
+        let path = temp_path(name);
+        let mut recorder = Recorder::new(&path, fps, Some(44100)).unwrap();
+        recorder.pending = Some(Image::new(1, 1));
+        let mut max_held = 0;
+        for frame in 0..frames {
+            recorder.push_samples(&vec![1; samples(frame) * CHANNELS]);
+            recorder.frame_finish().unwrap();
+            max_held = max_held.max(recorder.queue.len() / CHANNELS);
+        }
+        recorder.finish().unwrap();
+        let wav = std::fs::read(path.with_extension("wav")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("wav")).unwrap();
+        let data_size = u32::from_le_bytes(wav[40..44].try_into().unwrap()) as u64;
+        assert_eq!(wav.len() as u64, 44 + data_size);
+        (data_size / (CHANNELS * 2) as u64, max_held)
+    }
+
+    #[test]
+    fn audio_follows_frame_count() {
+        // 44100 / 60 is 735 stereo frames per video frame.
+        assert_eq!(audio_frames(60, 44100, 60.0), 44100);
+        // Behind: padded with silence.
+        assert_eq!(record("behind.y4m", 60.0, 60, |_| 700), (44100, 0));
+        // Ahead: only what the frames last is written, the rest is held up to 2 frames.
+        let (written, max_held) = record("ahead.y4m", 60.0, 60, |_| 3000);
+        assert_eq!(written, 44100);
+        assert_eq!(max_held, 1470);
+
+        // Drops whole stereo frames, keeping the odd sample at the end.
+        let mut queue = vec![1; 3000 * CHANNELS + 1];
+        align_audio(&mut queue, 735, 1470);
+        assert_eq!(queue.len(), 2204 * CHANNELS + 1);
+    }
+
+    #[test]
+    fn nes_frame_rate() {
+        // The NES runs at 60.0988 fps, about 733.8 stereo frames per video frame at 44100 Hz.
+        let expected = audio_frames(600, 44100, 60.0988);
+        assert_eq!(expected, 440275);
+        // An emulator generating 735 per frame is ahead by about 1.2 per frame, the excess
+        // is held for the next frames and then dropped.
+        let (written, max_held) = record("nes.y4m", 60.0988, 600, |_| 735);
+        assert_eq!(written, expected);
+        assert!(max_held > 0 && max_held <= 1468);
+        // Alternating 733 and 734 falls behind, and is padded. Rounding to whole frames can
+        // still hold one back.
+        let (written, max_held) = record("nes_behind.y4m", 60.0988, 600, |frame| {
+            733 + frame as usize % 2
+        });
+        assert_eq!(written, expected);
+        assert!(max_held <= 1);
+    }
+}
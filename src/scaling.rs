@@ -1,12 +1,14 @@
-/// The scaling strategy for the render target. A "VariableAspectRatio" option that preserves a minimum
-/// size while alowing vertical or horizontal aspects may be provided in the future.
+use sdl3::rect::Rect;
+
+/// The scaling strategy for the render target.
 /// To use the entire window without scaling simply draw directly to "canvas".
 
 #[derive(Debug, PartialEq, Default)]
 pub enum Scaling {
     #[default]
     /// Fits the render target to the window while preserving the aspect ratio with black bars.
-    /// Curenly does not work well with vertical aspect ratios, this will be fixed.
+    /// Works with both horizontal and vertical aspect ratios, using whichever window dimension
+    /// is the limiting one.
     PreserveAspect,
 
     /// Same as PreserveAspect, but performs integer scaling for best results with pixel art.
@@ -16,4 +18,159 @@ pub enum Scaling {
     /// Stretches the render target to the window, completely disregarding the aspect ratio. The
     /// Picture Gods will smite you if you release anything using this.
     StretchToWindow,
+
+    /// Adapts the render target resolution to the window's aspect ratio while always showing at
+    /// least "min_width" x "min_height" pixels. Uses integer scaling, and the remaining window
+    /// space becomes extra pixels instead of black bars, so wider (or taller) windows show more
+    /// of the game world. The pixel buffer and render target are recreated when the resolution
+    /// changes, use "App::resolution_changed" and "App::width/height" to react to it.
+    /// A minimum of zero is treated as 1.
+    Expand { min_width: u32, min_height: u32 },
+}
+
+// Calculates where a render target of "width" x "height" is drawn inside the window, in pixels.
+// "window_size" is in window coordinates, "dpi_mult" converts them to pixels.
+pub(crate) fn scaled_rect(
+    scaling: &Scaling,
+    width: u32,
+    height: u32,
+    window_size: (u32, u32),
+    dpi_mult: f32,
+) -> Rect {
+    match scaling {
+        Scaling::Integer | Scaling::PreserveAspect | Scaling::Expand { .. } => {
+            // The smallest scale of both axis ensures the result never overflows the window.
+            let scale_x = window_size.0 as f32 / width as f32;
+            let scale_y = window_size.1 as f32 / height as f32;
+            let scale = match scaling {
+                Scaling::Integer | Scaling::Expand { .. } => scale_x.min(scale_y).floor().max(1.0),
+                Scaling::PreserveAspect => scale_x.min(scale_y),
+                _ => 1.0,
+            };
+            let new_width = width as f32 * scale;
+            let new_height = height as f32 * scale;
+            let gap_x = ((window_size.0 as f32 - new_width) * dpi_mult) / 2.0;
+            let gap_y = ((window_size.1 as f32 - new_height) * dpi_mult) / 2.0;
+            Rect::new(
+                gap_x as i32,
+                gap_y as i32,
+                (new_width * dpi_mult) as u32,
+                (new_height * dpi_mult) as u32,
+            )
+        }
+        Scaling::StretchToWindow => Rect::new(
+            0,
+            0,
+            (window_size.0 as f32 * dpi_mult) as u32,
+            (window_size.1 as f32 * dpi_mult) as u32,
+        ),
+    }
+}
+
+// The render target size used by Scaling::Expand: the largest integer scale that still shows the
+// minimum area, with the remaining window space converted to extra pixels.
+pub(crate) fn expanded_size(
+    min_width: u32,
+    min_height: u32,
+    window_size: (u32, u32),
+) -> (u32, u32) {
+    let (min_width, min_height) = (min_width.max(1), min_height.max(1));
+    let scale = (window_size.0 / min_width)
+        .min(window_size.1 / min_height)
+        .max(1);
+    (
+        (window_size.0 / scale).max(min_width),
+        (window_size.1 / scale).max(min_height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserve_aspect_wide_window() {
+        // 320x240 in a 1280x720 window is limited by the height.
+        let rect = scaled_rect(&Scaling::PreserveAspect, 320, 240, (1280, 720), 1.0);
+        assert_eq!(rect, Rect::new(160, 0, 960, 720));
+    }
+
+    #[test]
+    fn preserve_aspect_tall_window() {
+        // 320x240 in a 640x960 window is limited by the width.
+        let rect = scaled_rect(&Scaling::PreserveAspect, 320, 240, (640, 960), 1.0);
+        assert_eq!(rect, Rect::new(0, 240, 640, 480));
+    }
+
+    #[test]
+    fn preserve_aspect_portrait_game() {
+        // A 180x320 portrait game in a 1920x1080 window.
+        let rect = scaled_rect(&Scaling::PreserveAspect, 180, 320, (1920, 1080), 1.0);
+        assert_eq!(rect.height(), 1080);
+        assert!(rect.width() <= 1920);
+        assert_eq!(rect, Rect::new(656, 0, 607, 1080));
+    }
+
+    #[test]
+    fn integer_uses_limiting_dimension() {
+        // Height allows 4x, but width only allows 2x.
+        let rect = scaled_rect(&Scaling::Integer, 320, 240, (700, 1000), 1.0);
+        assert_eq!(rect, Rect::new(30, 260, 640, 480));
+    }
+
+    #[test]
+    fn integer_never_below_1x() {
+        let rect = scaled_rect(&Scaling::Integer, 320, 240, (200, 100), 1.0);
+        assert_eq!(rect.width(), 320);
+        assert_eq!(rect.height(), 240);
+    }
+
+    #[test]
+    fn pixel_density_scales_result() {
+        let rect = scaled_rect(&Scaling::Integer, 320, 240, (640, 480), 2.0);
+        assert_eq!(rect, Rect::new(0, 0, 1280, 960));
+    }
+
+    #[test]
+    fn expand_widescreen() {
+        // 1920x1080 fits a 320x180 minimum exactly 6 times.
+        assert_eq!(expanded_size(320, 180, (1920, 1080)), (320, 180));
+        // Ultrawide shows more world horizontally.
+        assert_eq!(expanded_size(320, 180, (2560, 1080)), (426, 180));
+    }
+
+    #[test]
+    fn expand_tall_window() {
+        assert_eq!(expanded_size(320, 180, (1000, 1200)), (333, 400));
+    }
+
+    #[test]
+    fn expand_small_window() {
+        // Never smaller than the minimum, even if the window is.
+        assert_eq!(expanded_size(320, 180, (200, 100)), (320, 180));
+    }
+
+    #[test]
+    fn expand_zero_minimum() {
+        // Same as a minimum of 1, instead of dividing by zero.
+        assert_eq!(expanded_size(0, 180, (1920, 1080)), (320, 180));
+        assert_eq!(expanded_size(0, 0, (640, 480)), (1, 1));
+    }
+
+    #[test]
+    fn expand_fills_window() {
+        let (width, height) = expanded_size(320, 180, (2560, 1080));
+        let scaling = Scaling::Expand {
+            min_width: 320,
+            min_height: 180,
+        };
+        let rect = scaled_rect(&scaling, width, height, (2560, 1080), 1.0);
+        assert_eq!(rect, Rect::new(2, 0, 2556, 1080));
+    }
+
+    #[test]
+    fn stretch_to_window() {
+        let rect = scaled_rect(&Scaling::StretchToWindow, 320, 240, (640, 400), 2.0);
+        assert_eq!(rect, Rect::new(0, 0, 1280, 800));
+    }
 }
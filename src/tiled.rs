@@ -0,0 +1,392 @@
+// Loading of maps made with the Tiled editor (https://www.mapeditor.org), in TMX (XML) or
+// JSON format. Supports orthogonal, finite maps with a single tileset, either embedded or in
+// an external TSX/TSJ file, and layer data as CSV, XML or uncompressed base64.
+
+use crate::{Image, SdlResult, Tile, TileLayer, Tilemap, Tileset};
+use serde_json::Value;
+use std::path::Path;
+
+const FLIP_X: u32 = 0x8000_0000;
+const FLIP_Y: u32 = 0x4000_0000;
+// Diagonal flips (used for 90 degree rotations) and hexagonal rotations aren't supported,
+// but their bits still need to be removed from the tile id.
+const FLAG_MASK: u32 = 0xF000_0000;
+
+// The parts of a Tiled map we use, regardless of the file format.
+struct TiledMap {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    tilesets: Vec<TiledTileset>,
+    layers: Vec<(String, bool, Vec<u32>)>,
+}
+
+struct TiledTileset {
+    first_gid: u32,
+    image: std::path::PathBuf,
+    tile_width: u32,
+    tile_height: u32,
+    margin: u32,
+    spacing: u32,
+}
+
+impl Tilemap {
+    /// Loads a map made with Tiled, in TMX or JSON format, and the image of its tileset.
+    /// Only orthogonal, finite maps using a single tileset are supported. Tile layers inside
+    /// groups are flattened, other layer types are skipped.
+    pub fn load_tiled(path: impl AsRef<Path>) -> SdlResult<(Tilemap, Tileset)> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let map = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tmx") | Some("xml") => parse_tmx(&text, path)?,
+            _ => parse_json(&text, path)?,
+        };
+        let [tiled_tileset] = map.tilesets.as_slice() else {
+            return Err(error("Only maps with exactly one tileset are supported"));
+        };
+        let tileset = Tileset::with_spacing(
+            Image::load(&tiled_tileset.image)?,
+            tiled_tileset.tile_width,
+            tiled_tileset.tile_height,
+            tiled_tileset.margin,
+            tiled_tileset.spacing,
+        )?;
+
+        // Checked before allocating any layer, since the size comes from the file.
+        let cells = (map.width as usize)
+            .checked_mul(map.height as usize)
+            .ok_or_else(|| error("Map size is too large"))?;
+        let mut tilemap = Tilemap::new(map.width, map.height, map.tile_width, map.tile_height);
+        for (name, visible, gids) in map.layers {
+            if gids.len() != cells {
+                return Err(error("Layer data doesn't match the map size"));
+            }
+            let mut layer = TileLayer::new(name, map.width, map.height);
+            layer.visible = visible;
+            for (cell, gid) in layer.tiles_mut().iter_mut().zip(gids) {
+                *cell = decode_gid(gid, tiled_tileset.first_gid);
+            }
+            tilemap.layers.push(layer);
+        }
+        Ok((tilemap, tileset))
+    }
+}
+
+// Tiled ids start at "first_gid" (0 is an empty cell) and store flips in the highest bits.
+fn decode_gid(gid: u32, first_gid: u32) -> Option<Tile> {
+    let id = gid & !FLAG_MASK;
+    if id < first_gid.max(1) {
+        return None;
+    }
+    Some(Tile {
+        index: id - first_gid,
+        flip_x: gid & FLIP_X != 0,
+        flip_y: gid & FLIP_Y != 0,
+    })
+}
+
+fn error(message: &str) -> Box<dyn std::error::Error> {
+    Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message.to_string(),
+    ))
+}
+
+fn parse_json(text: &str, path: &Path) -> SdlResult<TiledMap> {
+    let json: Value = serde_json::from_str(text)?;
+    if json["infinite"].as_bool() == Some(true) {
+        return Err(error("Infinite Tiled maps are not supported"));
+    }
+    if json["orientation"]
+        .as_str()
+        .is_some_and(|o| o != "orthogonal")
+    {
+        return Err(error("Only orthogonal Tiled maps are supported"));
+    }
+    let mut tilesets = Vec::new();
+    for tileset in json["tilesets"].as_array().into_iter().flatten() {
+        let first_gid = json_u32(&tileset["firstgid"])?;
+        match tileset["source"].as_str() {
+            Some(source) => tilesets.push(load_external_tileset(
+                &path.with_file_name(source),
+                first_gid,
+            )?),
+            None => tilesets.push(json_tileset(tileset, path, first_gid)?),
+        }
+    }
+    let mut layers = Vec::new();
+    json_layers(&json["layers"], &mut layers)?;
+    Ok(TiledMap {
+        width: json_u32(&json["width"])?,
+        height: json_u32(&json["height"])?,
+        tile_width: json_u32(&json["tilewidth"])?,
+        tile_height: json_u32(&json["tileheight"])?,
+        tilesets,
+        layers,
+    })
+}
+
+fn json_layers(value: &Value, layers: &mut Vec<(String, bool, Vec<u32>)>) -> SdlResult<()> {
+    for layer in value.as_array().into_iter().flatten() {
+        match layer["type"].as_str() {
+            Some("group") => json_layers(&layer["layers"], layers)?,
+            Some("tilelayer") => {
+                let name = layer["name"].as_str().unwrap_or_default().to_string();
+                let visible = layer["visible"].as_bool().unwrap_or(true);
+                let data = match &layer["data"] {
+                    Value::Array(values) => {
+                        values.iter().map(json_u32).collect::<SdlResult<_>>()?
+                    }
+                    Value::String(text) => {
+                        let compression = layer["compression"].as_str().unwrap_or_default();
+                        decode_base64_data(text, compression)?
+                    }
+                    _ => return Err(error("Tile layer has no data")),
+                };
+                layers.push((name, visible, data));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// An embedded tileset, or the contents of a TSJ file.
+fn json_tileset(tileset: &Value, path: &Path, first_gid: u32) -> SdlResult<TiledTileset> {
+    let Some(image) = tileset["image"].as_str() else {
+        return Err(error("Only tilesets based on a single image are supported"));
+    };
+    Ok(TiledTileset {
+        first_gid,
+        image: path.with_file_name(image),
+        tile_width: json_u32(&tileset["tilewidth"])?,
+        tile_height: json_u32(&tileset["tileheight"])?,
+        margin: tileset["margin"].as_u64().unwrap_or(0) as u32,
+        spacing: tileset["spacing"].as_u64().unwrap_or(0) as u32,
+    })
+}
+
+fn json_u32(value: &Value) -> SdlResult<u32> {
+    value
+        .as_u64()
+        .map(|n| n as u32)
+        .ok_or_else(|| error("Expected a number in Tiled map"))
+}
+
+fn load_external_tileset(path: &Path, first_gid: u32) -> SdlResult<TiledTileset> {
+    let text = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("tsx") | Some("xml") => {
+            let document = roxmltree::Document::parse(&text)?;
+            xml_tileset(document.root_element(), path, first_gid)
+        }
+        _ => json_tileset(&serde_json::from_str(&text)?, path, first_gid),
+    }
+}
+
+fn parse_tmx(text: &str, path: &Path) -> SdlResult<TiledMap> {
+    let document = roxmltree::Document::parse(text)?;
+    let map = document.root_element();
+    if map.attribute("infinite") == Some("1") {
+        return Err(error("Infinite Tiled maps are not supported"));
+    }
+    if map
+        .attribute("orientation")
+        .is_some_and(|o| o != "orthogonal")
+    {
+        return Err(error("Only orthogonal Tiled maps are supported"));
+    }
+    let mut tilesets = Vec::new();
+    for tileset in map.children().filter(|node| node.has_tag_name("tileset")) {
+        let first_gid = xml_u32(tileset, "firstgid")?;
+        match tileset.attribute("source") {
+            Some(source) => tilesets.push(load_external_tileset(
+                &path.with_file_name(source),
+                first_gid,
+            )?),
+            None => tilesets.push(xml_tileset(tileset, path, first_gid)?),
+        }
+    }
+    let mut layers = Vec::new();
+    xml_layers(map, &mut layers)?;
+    Ok(TiledMap {
+        width: xml_u32(map, "width")?,
+        height: xml_u32(map, "height")?,
+        tile_width: xml_u32(map, "tilewidth")?,
+        tile_height: xml_u32(map, "tileheight")?,
+        tilesets,
+        layers,
+    })
+}
+
+fn xml_layers(
+    parent: roxmltree::Node,
+    layers: &mut Vec<(String, bool, Vec<u32>)>,
+) -> SdlResult<()> {
+    for node in parent.children().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "group" => xml_layers(node, layers)?,
+            "layer" => {
+                let name = node.attribute("name").unwrap_or_default().to_string();
+                let visible = node.attribute("visible") != Some("0");
+                let Some(data) = node.children().find(|child| child.has_tag_name("data")) else {
+                    return Err(error("Tile layer has no data"));
+                };
+                let text = data.text().unwrap_or_default();
+                let gids = match data.attribute("encoding") {
+                    Some("csv") => text
+                        .split(',')
+                        .map(|value| value.trim().parse::<u32>())
+                        .collect::<Result<_, _>>()?,
+                    Some("base64") => {
+                        let compression = data.attribute("compression").unwrap_or_default();
+                        decode_base64_data(text, compression)?
+                    }
+                    Some(_) => return Err(error("Unknown Tiled layer encoding")),
+                    None => data
+                        .children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        .map(|tile| tile.attribute("gid").unwrap_or("0").parse::<u32>())
+                        .collect::<Result<_, _>>()?,
+                };
+                layers.push((name, visible, gids));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// An embedded tileset, or the root of a TSX file.
+fn xml_tileset(tileset: roxmltree::Node, path: &Path, first_gid: u32) -> SdlResult<TiledTileset> {
+    let Some(image) = tileset.children().find(|child| child.has_tag_name("image")) else {
+        return Err(error("Only tilesets based on a single image are supported"));
+    };
+    let Some(source) = image.attribute("source") else {
+        return Err(error("Tileset image has no source"));
+    };
+    let optional = |name: &str| tileset.attribute(name).and_then(|value| value.parse().ok());
+    Ok(TiledTileset {
+        first_gid,
+        image: path.with_file_name(source),
+        tile_width: xml_u32(tileset, "tilewidth")?,
+        tile_height: xml_u32(tileset, "tileheight")?,
+        margin: optional("margin").unwrap_or(0),
+        spacing: optional("spacing").unwrap_or(0),
+    })
+}
+
+fn xml_u32(node: roxmltree::Node, name: &str) -> SdlResult<u32> {
+    let Some(value) = node.attribute(name) else {
+        return Err(error("Missing attribute in Tiled map"));
+    };
+    Ok(value.parse()?)
+}
+
+// Layer data as little endian u32 values, encoded in base64.
+fn decode_base64_data(text: &str, compression: &str) -> SdlResult<Vec<u32>> {
+    if !compression.is_empty() {
+        return Err(error(
+            "Compressed Tiled layers are not supported, use CSV or uncompressed base64",
+        ));
+    }
+    let bytes = decode_base64(text)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn decode_base64(text: &str) -> SdlResult<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return Err(error("Invalid base64 in Tiled layer")),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gid_flags() {
+        assert_eq!(decode_gid(0, 1), None);
+        assert_eq!(decode_gid(1, 1), Some(Tile::new(0)));
+        let tile = decode_gid(FLIP_X | FLIP_Y | 0x2000_0000 | 5, 1).unwrap();
+        assert_eq!((tile.index, tile.flip_x, tile.flip_y), (4, true, true));
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(
+            decode_base64("AQAAAAIAAAA=").unwrap(),
+            [1, 0, 0, 0, 2, 0, 0, 0]
+        );
+        assert_eq!(decode_base64_data("AQAAAAIAAAA=", "").unwrap(), [1, 2]);
+        assert!(decode_base64_data("AQAAAA==", "zlib").is_err());
+    }
+
+    #[test]
+    fn tmx_and_json_layers() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <map orientation="orthogonal" width="2" height="1" tilewidth="8" tileheight="8">
+              <tileset firstgid="1" tilewidth="8" tileheight="8" spacing="1">
+                <image source="tiles.png" width="17" height="8"/>
+              </tileset>
+              <layer name="ground" width="2" height="1">
+                <data encoding="csv">1,0</data>
+              </layer>
+              <group><layer name="top" visible="0"><data><tile gid="2"/><tile/></data></layer></group>
+            </map>"#;
+        let map = parse_tmx(tmx, Path::new("maps/level.tmx")).unwrap();
+        assert_eq!(map.tilesets[0].image, Path::new("maps/tiles.png"));
+        assert_eq!(map.tilesets[0].spacing, 1);
+        assert_eq!(map.layers[0], ("ground".to_string(), true, vec![1, 0]));
+        assert_eq!(map.layers[1], ("top".to_string(), false, vec![2, 0]));
+
+        let json = r#"{"width": 2, "height": 1, "tilewidth": 8, "tileheight": 8,
+            "orientation": "orthogonal", "infinite": false,
+            "tilesets": [{"firstgid": 1, "image": "tiles.png", "tilewidth": 8, "tileheight": 8}],
+            "layers": [{"type": "tilelayer", "name": "ground", "data": [1, 0]},
+                       {"type": "objectgroup", "name": "objects"}]}"#;
+        let map = parse_json(json, Path::new("level.json")).unwrap();
+        assert_eq!(map.layers.len(), 1);
+        assert_eq!(map.layers[0].2, vec![1, 0]);
+    }
+
+    #[test]
+    fn huge_map_is_rejected() {
+        let dir = std::env::temp_dir().join("mini_sdl_tiled_size_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        Image::new(8, 8).save(dir.join("tiles.png")).unwrap();
+        let json = r#"{"width": 4294967295, "height": 4294967295, "tilewidth": 8,
+            "tileheight": 8, "orientation": "orthogonal", "infinite": false,
+            "tilesets": [{"firstgid": 1, "image": "tiles.png", "tilewidth": 8, "tileheight": 8}],
+            "layers": [{"type": "tilelayer", "name": "ground", "data": [1, 0]}]}"#;
+        let path = dir.join("huge.json");
+        std::fs::write(&path, json).unwrap();
+        let error = Tilemap::load_tiled(&path).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let error = error.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,425 @@
+use crate::{BlitOptions, FrameBuffer, Image, SdlResult, Sprite, SpriteBatch};
+use sdl3::{
+    pixels::Color,
+    rect::Rect,
+    render::{Canvas, FRect, RenderTarget, Texture},
+};
+
+/// An image divided into a grid of equally sized tiles, numbered from left to right and
+/// top to bottom starting at 0. "margin" is the space around the whole grid and "spacing"
+/// the space between tiles, both in pixels.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub image: Image,
+    tile_width: u32,
+    tile_height: u32,
+    margin: u32,
+    spacing: u32,
+    columns: u32,
+    rows: u32,
+}
+
+impl Tileset {
+    /// A tileset with no margin or spacing.
+    pub fn new(image: Image, tile_width: u32, tile_height: u32) -> SdlResult<Self> {
+        Self::with_spacing(image, tile_width, tile_height, 0, 0)
+    }
+
+    /// A tileset with space around and between tiles.
+    pub fn with_spacing(
+        image: Image,
+        tile_width: u32,
+        tile_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> SdlResult<Self> {
+        if tile_width == 0 || tile_height == 0 {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Tile size can't be zero",
+            )));
+        }
+        let count =
+            |size: u32, tile: u32| (size.saturating_sub(margin * 2) + spacing) / (tile + spacing);
+        let columns = count(image.width(), tile_width);
+        let rows = count(image.height(), tile_height);
+        Ok(Self {
+            image,
+            tile_width,
+            tile_height,
+            margin,
+            spacing,
+            columns,
+            rows,
+        })
+    }
+
+    /// Width of a tile in pixels.
+    pub fn tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    /// Height of a tile in pixels.
+    pub fn tile_height(&self) -> u32 {
+        self.tile_height
+    }
+
+    /// How many tiles there are in each row of the image.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Total number of tiles.
+    pub fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// The area of the image a tile occupies, or None if the index is out of range.
+    pub fn source_rect(&self, index: u32) -> Option<Rect> {
+        if index >= self.tile_count() {
+            return None;
+        }
+        let column = index % self.columns;
+        let row = index / self.columns;
+        Some(Rect::new(
+            (self.margin + column * (self.tile_width + self.spacing)) as i32,
+            (self.margin + row * (self.tile_height + self.spacing)) as i32,
+            self.tile_width,
+            self.tile_height,
+        ))
+    }
+}
+
+/// A tile in a layer: its index in the tileset and how it's mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tile {
+    pub index: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Tile {
+    /// A tile that isn't flipped.
+    pub fn new(index: u32) -> Self {
+        Self {
+            index,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
+
+/// A grid of tiles, where None is an empty cell.
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<Tile>>,
+}
+
+impl TileLayer {
+    /// An empty, visible layer with the size in tiles. Panics if the number of cells doesn't
+    /// fit in a usize.
+    pub fn new(name: impl Into<String>, width: u32, height: u32) -> Self {
+        let cells = (width as usize)
+            .checked_mul(height as usize)
+            .expect("Layer size overflows usize");
+        Self {
+            name: name.into(),
+            visible: true,
+            width,
+            height,
+            tiles: vec![None; cells],
+        }
+    }
+
+    /// Width in tiles.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in tiles.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The tile at a cell. None if the cell is empty or outside the layer.
+    pub fn get(&self, x: i32, y: i32) -> Option<Tile> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return None;
+        }
+        self.tiles[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    /// Sets or clears a cell. Does nothing if it's outside the layer.
+    pub fn set(&mut self, x: i32, y: i32, tile: Option<Tile>) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        self.tiles[(y as u32 * self.width + x as u32) as usize] = tile;
+    }
+
+    /// All cells, row by row.
+    pub fn tiles(&self) -> &[Option<Tile>] {
+        &self.tiles
+    }
+
+    /// All cells, row by row.
+    pub fn tiles_mut(&mut self) -> &mut [Option<Tile>] {
+        &mut self.tiles
+    }
+}
+
+/// The visible area of a tilemap. "x" and "y" are the top-left corner in map pixels and can
+/// have fractional values for smooth scrolling, "width" and "height" are the size of the
+/// view in pixels, usually the pixel buffer or render target size.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Camera {
+    pub x: f32,
+    pub y: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Camera {
+    /// A camera at the map origin.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        }
+    }
+
+    /// Centers the camera on a point, in map pixels.
+    pub fn center_on(&mut self, x: f32, y: f32) {
+        self.x = x - self.width as f32 / 2.0;
+        self.y = y - self.height as f32 / 2.0;
+    }
+}
+
+/// Layers of tiles that share a size and a tileset. Layers are drawn in order, so the
+/// first one is at the bottom. Only the tiles visible through the camera are drawn.
+#[derive(Debug, Clone)]
+pub struct Tilemap {
+    pub layers: Vec<TileLayer>,
+    pub background: Option<Color>,
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+}
+
+impl Tilemap {
+    /// A map with no layers. Sizes are in tiles, tile sizes in pixels.
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            layers: Vec::new(),
+            background: None,
+            width,
+            height,
+            tile_width,
+            tile_height,
+        }
+    }
+
+    /// Adds an empty layer on top of the existing ones and returns it.
+    pub fn add_layer(&mut self, name: impl Into<String>) -> &mut TileLayer {
+        self.layers
+            .push(TileLayer::new(name, self.width, self.height));
+        self.layers.last_mut().unwrap()
+    }
+
+    /// Finds a layer by name.
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Finds a layer by name.
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// Width in tiles.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in tiles.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Width of a tile in pixels.
+    pub fn tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    /// Height of a tile in pixels.
+    pub fn tile_height(&self) -> u32 {
+        self.tile_height
+    }
+
+    /// The cell containing a point in map pixels.
+    pub fn cell_at(&self, x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / self.tile_width as f32).floor() as i32,
+            (y / self.tile_height as f32).floor() as i32,
+        )
+    }
+
+    /// Draws the visible layers into a FrameBuffer, usually the pixel buffer.
+    /// The camera position is rounded down to whole pixels.
+    pub fn draw_frame(&self, tileset: &Tileset, camera: &Camera, frame: &mut FrameBuffer) {
+        let (offset_x, offset_y) = (camera.x.floor() as i32, camera.y.floor() as i32);
+        if let Some(color) = self.background {
+            frame.fill_rect(0, 0, camera.width, camera.height, color);
+        }
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            self.visible_tiles(layer, camera, |x, y, tile| {
+                let Some(source) = tileset.source_rect(tile.index) else {
+                    return;
+                };
+                let options = BlitOptions {
+                    source: Some(source),
+                    flip_x: tile.flip_x,
+                    flip_y: tile.flip_y,
+                    blend: true,
+                    ..Default::default()
+                };
+                frame.blit_ex(
+                    &tileset.image,
+                    x * self.tile_width as i32 - offset_x,
+                    y * self.tile_height as i32 - offset_y,
+                    &options,
+                );
+            });
+        }
+    }
+
+    /// Draws the visible layers to a canvas, usually the render target inside
+    /// "canvas.with_texture_canvas", using "texture" created from the tileset image.
+    /// Keeps the sub-pixel camera position. All tiles are submitted in a single batch.
+    pub fn draw_canvas<T: RenderTarget>(
+        &self,
+        tileset: &Tileset,
+        texture: &Texture,
+        camera: &Camera,
+        batch: &mut SpriteBatch,
+        canvas: &mut Canvas<T>,
+    ) -> SdlResult<()> {
+        if let Some(color) = self.background {
+            canvas.set_draw_color(color);
+            let result = canvas.fill_rect(FRect::new(
+                0.0,
+                0.0,
+                camera.width as f32,
+                camera.height as f32,
+            ));
+            canvas.set_draw_color(Color::WHITE);
+            result?;
+        }
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            self.visible_tiles(layer, camera, |x, y, tile| {
+                let Some(source) = tileset.source_rect(tile.index) else {
+                    return;
+                };
+                let dest = FRect::new(
+                    x as f32 * tile_width - camera.x,
+                    y as f32 * tile_height - camera.y,
+                    tile_width,
+                    tile_height,
+                );
+                let mut sprite = Sprite::new(source, dest);
+                sprite.flip_x = tile.flip_x;
+                sprite.flip_y = tile.flip_y;
+                batch.push_ex(sprite);
+            });
+        }
+        batch.draw(canvas, texture)
+    }
+
+    // Calls "func" with the cell coordinates of each non-empty tile the camera can see.
+    fn visible_tiles(
+        &self,
+        layer: &TileLayer,
+        camera: &Camera,
+        mut func: impl FnMut(i32, i32, Tile),
+    ) {
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        let first_x = (camera.x / tile_width).floor().max(0.0) as i32;
+        let first_y = (camera.y / tile_height).floor().max(0.0) as i32;
+        let last_x = ((camera.x + camera.width as f32) / tile_width).ceil() as i32;
+        let last_y = ((camera.y + camera.height as f32) / tile_height).ceil() as i32;
+        let last_x = last_x.min(layer.width as i32);
+        let last_y = last_y.min(layer.height as i32);
+        for y in first_y..last_y {
+            for x in first_x..last_x {
+                if let Some(tile) = layer.get(x, y) {
+                    func(x, y, tile);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PixelFormat;
+
+    // Two 2x2 tiles side by side: solid red and solid green.
+    fn tileset() -> Tileset {
+        let mut image = Image::new(4, 2);
+        let mut frame = image.frame();
+        frame.fill_rect(0, 0, 2, 2, Color::RED);
+        frame.fill_rect(2, 0, 2, 2, Color::GREEN);
+        Tileset::new(image, 2, 2).unwrap()
+    }
+
+    #[test]
+    fn source_rects_with_spacing() {
+        let tileset = Tileset::with_spacing(Image::new(12, 7), 4, 4, 1, 2).unwrap();
+        assert_eq!(tileset.columns(), 2);
+        assert_eq!(tileset.tile_count(), 2);
+        let tileset = Tileset::with_spacing(Image::new(12, 12), 4, 4, 1, 1).unwrap();
+        assert_eq!(tileset.tile_count(), 4);
+        assert_eq!(tileset.source_rect(3), Some(Rect::new(6, 6, 4, 4)));
+        assert_eq!(tileset.source_rect(4), None);
+    }
+
+    #[test]
+    fn draws_with_camera_offset() {
+        let mut map = Tilemap::new(4, 4, 2, 2);
+        let layer = map.add_layer("ground");
+        layer.set(1, 0, Some(Tile::new(0)));
+        layer.set(2, 0, Some(Tile::new(1)));
+        let mut bytes = vec![0u8; 4 * 4 * 3];
+        let mut frame = FrameBuffer::new(&mut bytes, 12, 4, 4, PixelFormat::RGB24);
+        let mut camera = Camera::new(4, 4);
+        camera.x = 2.5;
+        map.draw_frame(&tileset(), &camera, &mut frame);
+        assert_eq!(frame.get(0, 0), Some(Color::RED));
+        assert_eq!(frame.get(1, 0), Some(Color::RED));
+        assert_eq!(frame.get(2, 0), Some(Color::GREEN));
+        assert_eq!(frame.get(0, 2), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn visible_tiles_are_clipped() {
+        let mut map = Tilemap::new(100, 100, 8, 8);
+        let layer = map.add_layer("ground");
+        layer.tiles_mut().fill(Some(Tile::new(0)));
+        let mut camera = Camera::new(16, 16);
+        camera.x = -4.0;
+        camera.y = 3.0;
+        let mut count = 0;
+        map.visible_tiles(&map.layers[0], &camera, |_, _, _| count += 1);
+        // Columns 0 and 1, rows 0 to 2
+        assert_eq!(count, 6);
+    }
+}
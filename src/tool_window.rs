@@ -0,0 +1,345 @@
+use crate::{
+    filter::{self, CachedTarget},
+    initialized, replace_texture, scaling, LayerKind, PixelFormat, Scaling, ScalingFilters,
+    SdlResult, Transform, Viewport,
+};
+use sdl3::{
+    event::{Event, WindowEvent},
+    rect::Rect,
+    render::{Canvas, Texture},
+    video::{VideoSubsystem, Window},
+};
+
+#[cfg(feature = "ttf")]
+use crate::{font_atlas, FontAtlas};
+
+/// An additional window created with "App::window_create", useful for things like tile palettes,
+/// memory viewers and other debug tools. Has its own canvas, optional pixel buffer and render
+/// target, scaling strategy and overlay. It is cleared on "App::frame_start" and presented on
+/// "App::frame_finish", along with the main window.
+///
+/// Textures are tied to the canvas that created them, so textures (and fonts) created from the
+/// main window can't be drawn here and vice-versa.
+pub struct ToolWindow {
+    /// Set to true when the user tries to close this window. Closing a tool window never quits
+    /// the App, use "App::window_close" to actually close it (or simply ignore it).
+    pub close_requested: bool,
+    /// Background color.
+    pub bg_color: (u8, u8, u8, u8),
+    /// Controls whether text overlay is visible.
+    pub display_overlay: bool,
+    /// The texture filter used when presenting the pixel buffer or render target, for each
+    /// scaling strategy.
+    pub filters: ScalingFilters,
+    /// The SDL canvas for this window. It is automatically cleared on every frame start.
+    pub canvas: Canvas<Window>,
+    /// Same as the App's render target, but for this window.
+    pub render_target: Option<Texture>,
+    /// Same as the App's pixel buffer, but for this window.
+    pub pixel_buffer: Option<Texture>,
+    /// Provides a default FontAtlas for the overlay. Must be loaded with "ToolWindow::font_load".
+    #[cfg(feature = "ttf")]
+    pub default_font: Option<FontAtlas>,
+    /// Scales the distance from line to line.
+    #[cfg(feature = "ttf")]
+    pub overlay_line_spacing: f32,
+    /// Scales the rendering of the entire overlay text.
+    #[cfg(feature = "ttf")]
+    pub overlay_scale: f32,
+    /// Initial coordinates (left, top) of the overlay text.
+    #[cfg(feature = "ttf")]
+    pub overlay_coords: sdl3::rect::Point,
+    #[cfg(feature = "ttf")]
+    overlay: Vec<String>,
+    width: u32,
+    height: u32,
+    dpi_mult: f32,
+    window_resized: bool,
+    resolution_changed: bool,
+    scaling: Scaling,
+    prescaler: CachedTarget,
+    events: Vec<Event>,
+}
+
+impl ToolWindow {
+    pub(crate) fn new(
+        video_subsystem: &VideoSubsystem,
+        name: &str,
+        width: u32,
+        height: u32,
+        scaling: Scaling,
+    ) -> SdlResult<Self> {
+        let mut window = video_subsystem
+            .window(name, width * 2, height * 2)
+            .high_pixel_density()
+            .resizable()
+            .opengl()
+            .build()?;
+        match scaling {
+            Scaling::Expand {
+                min_width,
+                min_height,
+            } => window.set_minimum_size(min_width, min_height)?,
+            _ => window.set_minimum_size(width, height)?,
+        }
+
+        let canvas = window.into_canvas();
+        let dpi_mult = canvas.window().pixel_density();
+
+        Ok(Self {
+            close_requested: false,
+            bg_color: (0, 0, 0, 255),
+            display_overlay: true,
+            filters: ScalingFilters::default(),
+            canvas,
+            render_target: None,
+            pixel_buffer: None,
+            #[cfg(feature = "ttf")]
+            default_font: None,
+            #[cfg(feature = "ttf")]
+            overlay_line_spacing: 1.0,
+            #[cfg(feature = "ttf")]
+            overlay_scale: 1.0,
+            #[cfg(feature = "ttf")]
+            overlay_coords: sdl3::rect::Point::new(16, 16),
+            #[cfg(feature = "ttf")]
+            overlay: Vec::with_capacity(100),
+            width,
+            height,
+            dpi_mult,
+            window_resized: false,
+            resolution_changed: false,
+            scaling,
+            prescaler: CachedTarget::default(),
+            events: Vec::new(),
+        })
+    }
+
+    /// The SDL window id, used to route events to this window.
+    pub fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// The render target width
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The render target height
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The ratio between window pixels and window coordinates.
+    pub fn pixel_density(&self) -> f32 {
+        self.dpi_mult
+    }
+
+    /// True if the window size or pixel density changed since the last frame start.
+    pub fn window_resized(&self) -> bool {
+        self.window_resized
+    }
+
+    /// True if the render target resolution changed since the last frame start, which only
+    /// happens when using "Scaling::Expand".
+    pub fn resolution_changed(&self) -> bool {
+        self.resolution_changed
+    }
+
+    /// Events sent to this window since the last frame start, like key presses and mouse
+    /// motion while it has focus. They are not seen by the main window's event handling.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Initializes the Pixel Buffer with the current width and height settings.
+    pub fn init_pixel_buffer(&mut self) -> SdlResult<()> {
+        replace_texture(
+            &mut self.canvas,
+            &mut self.pixel_buffer,
+            LayerKind::PixelBuffer,
+            PixelFormat::RGB24,
+            self.width,
+            self.height,
+        )
+    }
+
+    /// Initializes the Render Target with the current width and height settings.
+    pub fn init_render_target(&mut self) -> SdlResult<()> {
+        replace_texture(
+            &mut self.canvas,
+            &mut self.render_target,
+            LayerKind::RenderTarget,
+            PixelFormat::RGB24,
+            self.width,
+            self.height,
+        )
+    }
+
+    /// Adds a line of text to the overlay. The overlay text is cleared on every frame.
+    #[cfg(feature = "ttf")]
+    pub fn overlay_push(&mut self, text: impl Into<String>) {
+        self.overlay.push(text.into());
+    }
+
+    /// Loads a TTF font for this window's canvas. "fonts" is the App's TTF context.
+    #[cfg(feature = "ttf")]
+    pub fn font_load<P>(
+        &mut self,
+        fonts: &sdl3::ttf::Sdl3TtfContext,
+        path: P,
+        size: f32,
+        line_spacing: f32,
+    ) -> SdlResult<FontAtlas>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        FontAtlas::new(
+            path,
+            size,
+            line_spacing,
+            fonts,
+            &mut self.canvas.texture_creator(),
+        )
+    }
+
+    /// Uses SDL's "texture.with_lock" function to access the pixel buffer as an RGB array.
+    pub fn pixel_buffer_update<F, R>(&mut self, func: F) -> SdlResult<()>
+    where
+        F: FnOnce(&mut [u8], usize) -> R,
+    {
+        let buffer = initialized(&mut self.pixel_buffer, "Pixel buffer")?;
+        buffer.with_lock(None, func)?;
+        Ok(())
+    }
+
+    /// Where the render target is currently drawn in the window, with coordinate conversions.
+    pub fn viewport(&self) -> Viewport {
+        Viewport::new(
+            self.get_scaled_rect(),
+            self.width,
+            self.height,
+            self.dpi_mult,
+        )
+    }
+
+    fn get_scaled_rect(&self) -> Rect {
+        scaling::scaled_rect(
+            &self.scaling,
+            self.width,
+            self.height,
+            self.canvas.window().size(),
+            self.dpi_mult,
+        )
+    }
+
+    /// Presents the current pixel buffer respecting the scaling strategy.
+    pub fn pixel_buffer_present(&mut self) -> SdlResult<()> {
+        let size = (self.width, self.height);
+        let dest = Transform::default().apply(self.get_scaled_rect(), size);
+        filter::copy_filtered(
+            &mut self.canvas,
+            initialized(&mut self.pixel_buffer, "Pixel buffer")?,
+            size,
+            dest,
+            self.filters.get(&self.scaling),
+            &mut self.prescaler,
+            &Transform::default(),
+        )
+    }
+
+    /// Presents the render target to the canvas respecting the scaling strategy.
+    pub fn render_target_present(&mut self) -> SdlResult<()> {
+        let size = (self.width, self.height);
+        let dest = Transform::default().apply(self.get_scaled_rect(), size);
+        filter::copy_filtered(
+            &mut self.canvas,
+            initialized(&mut self.render_target, "Render Target")?,
+            size,
+            dest,
+            self.filters.get(&self.scaling),
+            &mut self.prescaler,
+            &Transform::default(),
+        )
+    }
+
+    // Receives every event with this window's id.
+    pub(crate) fn handle_event(&mut self, event: Event) {
+        if let Event::Window { win_event, .. } = &event {
+            match win_event {
+                WindowEvent::CloseRequested => self.close_requested = true,
+                WindowEvent::Resized(..)
+                | WindowEvent::PixelSizeChanged(..)
+                | WindowEvent::DisplayChanged(..) => {
+                    self.window_resized = true;
+                    self.dpi_mult = self.canvas.window().pixel_density();
+                }
+                _ => {}
+            }
+        }
+        self.events.push(event);
+    }
+
+    // Same as App::set_size.
+    fn set_size(&mut self, w: u32, h: u32) -> SdlResult<()> {
+        if w == self.width && h == self.height {
+            return Ok(());
+        }
+        self.width = w;
+        self.height = h;
+        self.resolution_changed = true;
+
+        if self.pixel_buffer.is_some() {
+            self.init_pixel_buffer()?;
+        }
+
+        if self.render_target.is_some() {
+            self.init_render_target()?;
+        }
+
+        Ok(())
+    }
+
+    // Adapts the resolution to the window when using Scaling::Expand. Called after events are processed.
+    pub(crate) fn update_resolution(&mut self) -> SdlResult<()> {
+        if let Scaling::Expand {
+            min_width,
+            min_height,
+        } = self.scaling
+        {
+            let (w, h) = scaling::expanded_size(min_width, min_height, self.canvas.window().size());
+            self.set_size(w, h)?;
+        }
+        Ok(())
+    }
+
+    // Called before events are processed.
+    pub(crate) fn frame_start(&mut self) {
+        self.events.clear();
+        self.window_resized = false;
+        self.resolution_changed = false;
+        self.canvas.set_draw_color(self.bg_color);
+        self.canvas.clear();
+        self.canvas.set_draw_color((255, 255, 255, 255));
+    }
+
+    pub(crate) fn frame_finish(&mut self) -> SdlResult<()> {
+        #[cfg(feature = "ttf")]
+        {
+            if self.display_overlay {
+                if let Some(font) = &mut self.default_font {
+                    font_atlas::draw_overlay(
+                        font,
+                        &mut self.overlay,
+                        self.overlay_coords,
+                        self.overlay_scale,
+                        self.overlay_line_spacing,
+                        &mut self.canvas,
+                    )?;
+                }
+            }
+        }
+        self.canvas.present();
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use crate::SdlResult;
+use sdl3::{
+    pixels::FColor,
+    rect::Rect,
+    render::{Canvas, FPoint, FRect, Texture, Vertex},
+    video::Window,
+};
+
+/// Moves, zooms, rotates and flips the presented image, on top of the Scaling strategy.
+/// Applied by "pixel_buffer_present", "render_target_present" and "present_layers" without
+/// any extra render pass, along with the letterbox image and transitions. Letterbox color
+/// bars stay aligned to the window. Note that "App::viewport" ignores the transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Offset in render target pixels.
+    pub offset: (f32, f32),
+    /// Scale around the center of the scaled rect. 1.0 is the regular size.
+    pub zoom: f32,
+    /// Rotation in degrees, clockwise around the center.
+    pub rotation: f64,
+    /// Mirrors the image horizontally.
+    pub flip_x: bool,
+    /// Mirrors the image vertically.
+    pub flip_y: bool,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            offset: (0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
+
+impl Transform {
+    // The destination of an image of "source_size" pixels scaled to "rect", after applying
+    // the offset and zoom. Rotation and flips are applied when copying.
+    pub(crate) fn apply(&self, rect: Rect, source_size: (u32, u32)) -> FRect {
+        let scale_x = rect.width() as f32 / source_size.0 as f32;
+        let scale_y = rect.height() as f32 / source_size.1 as f32;
+        let width = rect.width() as f32 * self.zoom;
+        let height = rect.height() as f32 * self.zoom;
+        let center_x = rect.x() as f32 + rect.width() as f32 / 2.0 + self.offset.0 * scale_x;
+        let center_y = rect.y() as f32 + rect.height() as f32 / 2.0 + self.offset.1 * scale_y;
+        FRect::new(
+            center_x - width / 2.0,
+            center_y - height / 2.0,
+            width,
+            height,
+        )
+    }
+
+    // Copies the "source" area of "texture" to "target", which is a part of "dest", the
+    // destination of the whole image given by "apply". Rotation and flips are applied around
+    // the center of "dest", so an image drawn in parts lines up with one drawn whole.
+    pub(crate) fn copy(
+        &self,
+        canvas: &mut Canvas<Window>,
+        texture: &Texture,
+        source: Option<FRect>,
+        target: FRect,
+        dest: FRect,
+    ) -> SdlResult<()> {
+        let (target, center) = self.place(target, dest);
+        canvas.copy_ex(
+            texture,
+            source,
+            target,
+            self.rotation,
+            center,
+            self.flip_x,
+            self.flip_y,
+        )?;
+        Ok(())
+    }
+
+    // Mirrors "target" inside "dest" if flipped, and returns it with the center of "dest"
+    // relative to it, which is the rotation center used by "copy_ex".
+    fn place(&self, target: FRect, dest: FRect) -> (FRect, FPoint) {
+        let x = if self.flip_x {
+            dest.x * 2.0 + dest.w - target.x - target.w
+        } else {
+            target.x
+        };
+        let y = if self.flip_y {
+            dest.y * 2.0 + dest.h - target.y - target.h
+        } else {
+            target.y
+        };
+        let center = FPoint::new(dest.x + dest.w / 2.0 - x, dest.y + dest.h / 2.0 - y);
+        (FRect::new(x, y, target.w, target.h), center)
+    }
+
+    // Fills "dest" with "color", rotated like the image copied there.
+    pub(crate) fn fill(
+        &self,
+        canvas: &mut Canvas<Window>,
+        dest: FRect,
+        color: (u8, u8, u8, u8),
+    ) -> SdlResult<()> {
+        let color = FColor {
+            r: color.0 as f32 / 255.0,
+            g: color.1 as f32 / 255.0,
+            b: color.2 as f32 / 255.0,
+            a: color.3 as f32 / 255.0,
+        };
+        let vertices = self.corners(dest).map(|(x, y)| Vertex {
+            position: FPoint::new(x, y),
+            color,
+            tex_coord: FPoint::new(0.0, 0.0),
+        });
+        canvas.render_geometry(&vertices, None, &[0, 1, 2, 2, 3, 0])?;
+        Ok(())
+    }
+
+    // Corners of "dest" rotated around its center, clockwise from the top-left.
+    fn corners(&self, dest: FRect) -> [(f32, f32); 4] {
+        let (half_w, half_h) = (dest.w / 2.0, dest.h / 2.0);
+        let (center_x, center_y) = (dest.x + half_w, dest.y + half_h);
+        let (sin, cos) = (self.rotation.to_radians() as f32).sin_cos();
+        [
+            (-half_w, -half_h),
+            (half_w, -half_h),
+            (half_w, half_h),
+            (-half_w, half_h),
+        ]
+        .map(|(x, y)| (center_x + x * cos - y * sin, center_y + x * sin + y * cos))
+    }
+
+    // This transform with the screen shake added to it.
+    pub(crate) fn with_shake(&self, shake: &ScreenShake) -> Transform {
+        let (x, y) = shake.offset();
+        Transform {
+            offset: (self.offset.0 + x, self.offset.1 + y),
+            rotation: self.rotation + shake.rotation(),
+            ..*self
+        }
+    }
+}
+
+/// Screen shake driven by "trauma", from 0.0 to 1.0. Adding trauma on impacts and letting
+/// it decay produces natural looking shakes, since the intensity is trauma squared.
+/// Updated automatically by "App::frame_start" and added to "App::transform" when presenting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenShake {
+    /// The current amount of shake. Decays towards 0.0 over time.
+    pub trauma: f32,
+    /// How much trauma is lost per second.
+    pub decay: f32,
+    /// Maximum offset in render target pixels, at full trauma.
+    pub max_offset: f32,
+    /// Maximum rotation in degrees, at full trauma.
+    pub max_rotation: f64,
+    /// How fast the shake moves. Higher values are more jittery.
+    pub frequency: f32,
+    time: f32,
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            decay: 1.0,
+            max_offset: 8.0,
+            max_rotation: 2.0,
+            frequency: 25.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl ScreenShake {
+    /// Adds trauma, clamped to 1.0.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// The current offset in render target pixels.
+    pub fn offset(&self) -> (f32, f32) {
+        let amount = self.intensity() * self.max_offset;
+        (amount * self.noise(0.0), amount * self.noise(17.0))
+    }
+
+    /// The current rotation in degrees.
+    pub fn rotation(&self) -> f64 {
+        self.intensity() as f64 * self.max_rotation * self.noise(43.0) as f64
+    }
+
+    // Advances time and decays the trauma.
+    pub(crate) fn update(&mut self, elapsed_time: f64) {
+        if self.trauma <= 0.0 {
+            return;
+        }
+        self.time += elapsed_time as f32;
+        self.trauma = (self.trauma - self.decay * elapsed_time as f32).max(0.0);
+    }
+
+    fn intensity(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+
+    // Smooth, deterministic noise from -1.0 to 1.0. Each seed is a different channel.
+    fn noise(&self, seed: f32) -> f32 {
+        let t = self.time * self.frequency;
+        ((t + seed).sin() + (t * 2.3 + seed * 1.7).sin() * 0.5) / 1.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_and_offset() {
+        let transform = Transform {
+            offset: (10.0, 0.0),
+            zoom: 2.0,
+            ..Default::default()
+        };
+        // 2x scale, so the offset is 20 window pixels.
+        let dest = transform.apply(Rect::new(0, 0, 200, 100), (100, 50));
+        assert_eq!(dest, FRect::new(-80.0, -50.0, 400.0, 200.0));
+    }
+
+    #[test]
+    fn flipped_parts_are_mirrored() {
+        let transform = Transform {
+            flip_x: true,
+            ..Default::default()
+        };
+        let dest = FRect::new(100.0, 0.0, 100.0, 50.0);
+        // The left quarter of the image ends up on the right.
+        let (target, center) = transform.place(FRect::new(100.0, 0.0, 25.0, 50.0), dest);
+        assert_eq!(target, FRect::new(175.0, 0.0, 25.0, 50.0));
+        assert_eq!((center.x, center.y), (-25.0, 25.0));
+        // The whole image stays in place, rotating around its own center.
+        let (target, center) = transform.place(dest, dest);
+        assert_eq!(target, dest);
+        assert_eq!((center.x, center.y), (50.0, 25.0));
+    }
+
+    #[test]
+    fn shake_decays() {
+        let mut shake = ScreenShake::default();
+        assert_eq!(shake.offset(), (0.0, 0.0));
+        shake.add_trauma(2.0);
+        assert_eq!(shake.trauma, 1.0);
+        shake.update(0.1);
+        let (x, y) = shake.offset();
+        assert!(x.abs() <= shake.max_offset && y.abs() <= shake.max_offset);
+        assert!(x != 0.0 || y != 0.0);
+        shake.update(1.0);
+        assert_eq!(shake.trauma, 0.0);
+        assert_eq!(shake.rotation(), 0.0);
+    }
+}
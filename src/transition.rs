@@ -0,0 +1,343 @@
+use crate::{filter::CachedTarget, Filter, SdlResult, Transform};
+use sdl3::{
+    rect::Rect,
+    render::{BlendMode, Canvas, FRect, ScaleMode, Texture},
+    video::Window,
+};
+
+// The largest block size of "Transition::Pixelate", in render target pixels.
+const MAX_PIXELATE_BLOCK: f64 = 16.0;
+
+/// A visual transition between the previous frame and the new one. Started with
+/// "App::transition_start" and drawn by "pixel_buffer_present", "render_target_present" and
+/// "present_layers".
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Transition {
+    /// The outgoing frame fades to a color during the first half, then the incoming one
+    /// fades in from it.
+    Fade((u8, u8, u8)),
+
+    /// The outgoing frame gradually becomes transparent, revealing the incoming one.
+    Crossfade,
+
+    /// The incoming frame is revealed from left to right.
+    WipeHorizontal,
+
+    /// The incoming frame is revealed from top to bottom.
+    WipeVertical,
+
+    /// The outgoing frame breaks into increasingly large blocks during the first half, then
+    /// the incoming one resolves from large blocks back to full detail.
+    Pixelate,
+
+    /// The incoming frame is revealed by a growing circle in the center.
+    Iris,
+}
+
+// A running transition, with a copy of the outgoing frame.
+pub(crate) struct TransitionState {
+    kind: Transition,
+    duration: f64,
+    time: f64,
+    outgoing: CachedTarget,
+    outgoing_size: (u32, u32),
+    pixelate: CachedTarget,
+}
+
+impl TransitionState {
+    // Starts from "outgoing", a snapshot of "size" pixels taken by "snapshot".
+    pub(crate) fn new(
+        kind: Transition,
+        duration: f64,
+        outgoing: CachedTarget,
+        size: (u32, u32),
+    ) -> Self {
+        Self {
+            kind,
+            duration: duration.max(f64::EPSILON),
+            time: 0.0,
+            outgoing,
+            outgoing_size: size,
+            pixelate: CachedTarget::default(),
+        }
+    }
+
+    // Advances the time, returns false when finished.
+    pub(crate) fn update(&mut self, elapsed_time: f64) -> bool {
+        self.time += elapsed_time;
+        self.time < self.duration
+    }
+
+    // From 0.0 at the start to 1.0 at the end.
+    pub(crate) fn progress(&self) -> f64 {
+        (self.time / self.duration).clamp(0.0, 1.0)
+    }
+
+    pub(crate) fn destroy(self) {
+        self.outgoing.destroy();
+        self.pixelate.destroy();
+    }
+
+    // Draws the transition at "dest", on top of the incoming frame already drawn there with
+    // "transform", which is applied the same way here.
+    pub(crate) fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        incoming: &mut Texture,
+        incoming_size: (u32, u32),
+        dest: FRect,
+        filter: Filter,
+        transform: &Transform,
+    ) -> SdlResult<()> {
+        let t = self.progress();
+        let (width, height) = (self.outgoing_size.0 as f32, self.outgoing_size.1 as f32);
+        let scale_x = dest.w / width;
+        let scale_y = dest.h / height;
+        let size = self.outgoing_size;
+        let outgoing = self.outgoing.get(canvas, size.0, size.1)?;
+        outgoing.set_scale_mode(match filter {
+            Filter::Linear => ScaleMode::Linear,
+            _ => ScaleMode::Nearest,
+        });
+        outgoing.set_alpha_mod(255);
+
+        // Copies an area of the outgoing frame, in its own pixels, to the same area in "dest".
+        let copy_area = |canvas: &mut Canvas<Window>, source: FRect| {
+            let target = FRect::new(
+                dest.x + source.x * scale_x,
+                dest.y + source.y * scale_y,
+                source.w * scale_x,
+                source.h * scale_y,
+            );
+            transform.copy(canvas, outgoing, Some(source), target, dest)
+        };
+
+        match self.kind {
+            Transition::Fade(color) => {
+                if t < 0.5 {
+                    copy_area(canvas, FRect::new(0.0, 0.0, width, height))?;
+                }
+                let alpha = fade_alpha(t);
+                let blend_mode = canvas.blend_mode();
+                canvas.set_blend_mode(BlendMode::Blend);
+                let result = transform.fill(canvas, dest, (color.0, color.1, color.2, alpha));
+                canvas.set_blend_mode(blend_mode);
+                result?;
+            }
+            Transition::Crossfade => {
+                outgoing.set_alpha_mod(((1.0 - t) * 255.0) as u8);
+                transform.copy(canvas, outgoing, None, dest, dest)?;
+            }
+            Transition::WipeHorizontal | Transition::WipeVertical | Transition::Iris => {
+                for area in outgoing_areas(self.kind, t, size) {
+                    copy_area(canvas, area)?;
+                }
+            }
+            Transition::Pixelate => {
+                let amount = 1.0 - (t * 2.0 - 1.0).abs();
+                let block = 1.0 + amount * (MAX_PIXELATE_BLOCK - 1.0);
+                let pixelated = &mut self.pixelate;
+                if t < 0.5 {
+                    pixelate(canvas, outgoing, size, pixelated, block, dest, transform)?;
+                } else {
+                    // Relative to the render target, in case the incoming frame is larger.
+                    let block = block * incoming_size.0 as f64 / size.0 as f64;
+                    pixelate(
+                        canvas,
+                        incoming,
+                        incoming_size,
+                        pixelated,
+                        block,
+                        dest,
+                        transform,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Copies "frame" (of "size" pixels) into "snapshot". Done on every present, so a transition
+// started later begins from the frame on screen, even if the buffers were drawn to since.
+pub(crate) fn snapshot(
+    canvas: &mut Canvas<Window>,
+    frame: &mut Texture,
+    size: (u32, u32),
+    snapshot: &mut CachedTarget,
+) -> SdlResult<()> {
+    let target = snapshot.get(canvas, size.0, size.1)?;
+    frame.set_scale_mode(ScaleMode::Nearest);
+    // Copies alpha as is, the snapshot is blended when drawn.
+    let blend_mode = frame.blend_mode();
+    frame.set_blend_mode(BlendMode::None);
+    let mut result = Ok(());
+    canvas.with_texture_canvas(target, |target| {
+        target.set_draw_color((0, 0, 0, 0));
+        target.clear();
+        result = target.copy(frame, None, None);
+    })?;
+    frame.set_blend_mode(blend_mode);
+    result?;
+    Ok(())
+}
+
+// The color's opacity during "Transition::Fade", peaking halfway.
+fn fade_alpha(t: f64) -> u8 {
+    let amount = 1.0 - (t * 2.0 - 1.0).abs();
+    (amount * 255.0).round() as u8
+}
+
+// The areas of the outgoing frame (of "size" pixels) still covering the incoming one during
+// the wipes and "Transition::Iris", in the outgoing frame's pixels.
+fn outgoing_areas(kind: Transition, t: f64, size: (u32, u32)) -> Vec<FRect> {
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    let mut areas = Vec::new();
+    match kind {
+        Transition::WipeHorizontal => {
+            let edge = t as f32 * width;
+            areas.push(FRect::new(edge, 0.0, width - edge, height));
+        }
+        Transition::WipeVertical => {
+            let edge = t as f32 * height;
+            areas.push(FRect::new(0.0, edge, width, height - edge));
+        }
+        Transition::Iris => {
+            let (center_x, center_y) = (width / 2.0, height / 2.0);
+            let radius = t as f32 * (center_x * center_x + center_y * center_y).sqrt();
+            // One row at a time, covering what's outside the circle.
+            for row in 0..size.1 {
+                let y = row as f32 + 0.5 - center_y;
+                if y.abs() >= radius {
+                    areas.push(FRect::new(0.0, row as f32, width, 1.0));
+                    continue;
+                }
+                let half = (radius * radius - y * y).sqrt();
+                let left = (center_x - half).max(0.0).round();
+                let right = (center_x + half).min(width).round();
+                areas.push(FRect::new(0.0, row as f32, left, 1.0));
+                areas.push(FRect::new(right, row as f32, width - right, 1.0));
+            }
+        }
+        _ => {}
+    }
+    areas.retain(|area| area.w > 0.0 && area.h > 0.0);
+    areas
+}
+
+// Draws "texture" at "dest" with its pixels grouped into blocks of "block" pixels, by
+// shrinking it into "small" and scaling it back up without filtering.
+fn pixelate(
+    canvas: &mut Canvas<Window>,
+    texture: &mut Texture,
+    size: (u32, u32),
+    small: &mut CachedTarget,
+    block: f64,
+    dest: FRect,
+    transform: &Transform,
+) -> SdlResult<()> {
+    let small_width = ((size.0 as f64 / block).ceil() as u32).max(1);
+    let small_height = ((size.1 as f64 / block).ceil() as u32).max(1);
+    let area = Rect::new(0, 0, small_width, small_height);
+    // Sized to the full frame, so it isn't recreated as the block size changes.
+    let target = small.get(canvas, size.0, size.1)?;
+    texture.set_scale_mode(ScaleMode::Nearest);
+    let mut result = Ok(());
+    canvas.with_texture_canvas(target, |target| {
+        target.set_draw_color((0, 0, 0, 0));
+        target.clear();
+        result = target.copy(texture, None, area);
+    })?;
+    result?;
+    target.set_scale_mode(ScaleMode::Nearest);
+    let source = FRect::new(0.0, 0.0, small_width as f32, small_height as f32);
+    transform.copy(canvas, target, Some(source), dest, dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(duration: f64) -> TransitionState {
+        TransitionState::new(
+            Transition::Crossfade,
+            duration,
+            CachedTarget::default(),
+            (320, 240),
+        )
+    }
+
+    #[test]
+    fn progress() {
+        let mut transition = state(2.0);
+        assert_eq!(transition.progress(), 0.0);
+        transition.update(0.5);
+        assert_eq!(transition.progress(), 0.25);
+        transition.update(1.0);
+        assert_eq!(transition.progress(), 0.75);
+        // Clamped when the last frame overshoots.
+        transition.update(1.0);
+        assert_eq!(transition.progress(), 1.0);
+    }
+
+    #[test]
+    fn finishes_at_duration() {
+        let mut transition = state(1.0);
+        assert!(transition.update(0.5));
+        assert!(transition.update(0.25));
+        assert!(!transition.update(0.25));
+    }
+
+    #[test]
+    fn zero_duration_finishes_on_first_update() {
+        let mut transition = state(0.0);
+        assert!(!transition.update(1.0 / 60.0));
+        assert_eq!(transition.progress(), 1.0);
+    }
+
+    #[test]
+    fn fade_peaks_halfway() {
+        assert_eq!(fade_alpha(0.0), 0);
+        assert_eq!(fade_alpha(0.25), 128);
+        assert_eq!(fade_alpha(0.5), 255);
+        assert_eq!(fade_alpha(1.0), 0);
+    }
+
+    #[test]
+    fn wipe_areas() {
+        let areas = outgoing_areas(Transition::WipeHorizontal, 0.25, (320, 240));
+        assert_eq!(areas, [FRect::new(80.0, 0.0, 240.0, 240.0)]);
+        let areas = outgoing_areas(Transition::WipeVertical, 0.5, (320, 240));
+        assert_eq!(areas, [FRect::new(0.0, 120.0, 320.0, 120.0)]);
+        assert!(outgoing_areas(Transition::WipeVertical, 1.0, (320, 240)).is_empty());
+    }
+
+    #[test]
+    fn iris_row_spans() {
+        // Covered row by row at the start, uncovered at the end.
+        let areas = outgoing_areas(Transition::Iris, 0.0, (8, 8));
+        assert_eq!(areas.len(), 8);
+        assert!(areas.iter().all(|area| (area.x, area.w) == (0.0, 8.0)));
+        assert!(outgoing_areas(Transition::Iris, 1.0, (8, 8)).is_empty());
+
+        // Halfway the radius is about 2.83: the middle rows have a symmetric hole, the top
+        // and bottom rows are still covered.
+        let areas = outgoing_areas(Transition::Iris, 0.5, (8, 8));
+        let row = |y: f32| -> Vec<FRect> { areas.iter().filter(|a| a.y == y).copied().collect() };
+        assert_eq!(row(0.0), [FRect::new(0.0, 0.0, 8.0, 1.0)]);
+        assert_eq!(
+            row(3.0),
+            [
+                FRect::new(0.0, 3.0, 1.0, 1.0),
+                FRect::new(7.0, 3.0, 1.0, 1.0)
+            ]
+        );
+        assert_eq!(
+            row(4.0),
+            [
+                FRect::new(0.0, 4.0, 1.0, 1.0),
+                FRect::new(7.0, 4.0, 1.0, 1.0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use sdl3::rect::Rect;
+
+/// Describes where the render target is currently drawn inside the window, and converts
+/// coordinates between the window (i.e. mouse coordinates) and the render target.
+/// Obtained via "App::viewport", and only valid until the window or resolution changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// The destination rect in window pixels (not window coordinates, see "dpi_mult").
+    pub rect: Rect,
+    /// How many window pixels each render target pixel covers horizontally.
+    pub scale_x: f32,
+    /// How many window pixels each render target pixel covers vertically.
+    pub scale_y: f32,
+    /// The ratio between window pixels and window coordinates, i.e. 2.0 on most Retina displays.
+    pub dpi_mult: f32,
+}
+
+impl Viewport {
+    pub(crate) fn new(rect: Rect, width: u32, height: u32, dpi_mult: f32) -> Self {
+        Self {
+            rect,
+            scale_x: rect.width() as f32 / width as f32,
+            scale_y: rect.height() as f32 / height as f32,
+            dpi_mult,
+        }
+    }
+
+    /// Converts window coordinates (the ones provided by mouse events) to render target pixels.
+    /// The result may be outside the render target, use "contains" to check.
+    pub fn window_to_buffer(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            ((x * self.dpi_mult) - self.rect.x() as f32) / self.scale_x,
+            ((y * self.dpi_mult) - self.rect.y() as f32) / self.scale_y,
+        )
+    }
+
+    /// Converts render target pixels to window coordinates.
+    pub fn buffer_to_window(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            ((x * self.scale_x) + self.rect.x() as f32) / self.dpi_mult,
+            ((y * self.scale_y) + self.rect.y() as f32) / self.dpi_mult,
+        )
+    }
+
+    /// True if the window coordinates are inside the render target.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let (x, y) = (x * self.dpi_mult, y * self.dpi_mult);
+        x >= self.rect.left() as f32
+            && x < self.rect.right() as f32
+            && y >= self.rect.top() as f32
+            && y < self.rect.bottom() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // 320x240 at 3x, centered in a 1280x720 window on a 2x display.
+        let viewport = Viewport::new(Rect::new(320, 0, 1920, 1440), 320, 240, 2.0);
+        let (x, y) = viewport.window_to_buffer(160.0, 0.0);
+        assert_eq!((x, y), (0.0, 0.0));
+        let (x, y) = viewport.window_to_buffer(400.0, 360.0);
+        assert_eq!((x, y), (80.0, 120.0));
+        assert_eq!(viewport.buffer_to_window(80.0, 120.0), (400.0, 360.0));
+    }
+
+    #[test]
+    fn contains() {
+        let viewport = Viewport::new(Rect::new(160, 0, 960, 720), 320, 240, 1.0);
+        assert!(viewport.contains(160.0, 0.0));
+        assert!(!viewport.contains(159.0, 0.0));
+        assert!(!viewport.contains(1120.0, 100.0));
+    }
+}
@@ -0,0 +1,17 @@
+use sdl3::video::DisplayMode;
+
+/// The presentation mode of the main window. Use "App::set_window_mode" to switch between them.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum WindowMode {
+    #[default]
+    /// A regular, resizable window with decorations.
+    Windowed,
+
+    /// Covers the entire display without changing its resolution. Switching in and out of it
+    /// is fast, and is usually the best option for pixel art games.
+    BorderlessFullscreen,
+
+    /// Changes the display to the provided mode. Use "App::display_modes" to obtain the list of
+    /// modes supported by the current display.
+    ExclusiveFullscreen(DisplayMode),
+}